@@ -22,7 +22,8 @@ pub use skipdb_core::{
   iter::*,
   range::*,
   rev_iter::*,
-  types::{Ref, ValueRef},
+  types::{CommittedRef, CompactionStats, Ref, ValueRef},
+  versions::*,
 };
 
 use skipdb_core::{AsSkipCore, Database, SkipCore};
@@ -30,7 +31,7 @@ use skipdb_core::{AsSkipCore, Database, SkipCore};
 mod read;
 pub use read::*;
 
-pub use async_txn::{AsyncSpawner, BTreePwm, Detach};
+pub use async_txn::{AsyncSpawner, BTreePwm, Detach, TransactionState, TransactionStats};
 
 #[cfg(feature = "smol")]
 #[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
@@ -1,10 +1,21 @@
-use std::{collections::hash_map::RandomState, hash::Hash};
+use std::{collections::hash_map::RandomState, convert::Infallible, hash::Hash};
+
+use async_txn::{
+  error::{OracleError, WtmError},
+  Entry, EntryData,
+};
 
 use super::*;
 
 mod write;
 pub use write::*;
 
+mod lock;
+pub use lock::*;
+
+mod metadata;
+pub use metadata::*;
+
 #[cfg(all(test, any(feature = "tokio", feature = "smol", feature = "async-std")))]
 mod tests;
 
@@ -30,18 +41,22 @@ where
   tm: AsyncTm<K, V, HashCm<K, S>, BTreePwm<K, V>, SP>,
   map: SkipCore<K, V>,
   hasher: S,
+  locks: LockTable<K, S>,
 }
 
-impl<K, V, SP: AsyncSpawner, S> Inner<K, V, SP, S> {
+impl<K, V, SP: AsyncSpawner, S: Clone> Inner<K, V, SP, S> {
   async fn new(name: &str, hasher: S) -> Self {
     let tm = AsyncTm::<_, _, _, _, SP>::new(name, 0).await;
     Self {
       tm,
       map: SkipCore::new(),
+      locks: LockTable::with_hasher(hasher.clone()),
       hasher,
     }
   }
+}
 
+impl<K, V, SP: AsyncSpawner, S> Inner<K, V, SP, S> {
   async fn version(&self) -> u64 {
     self.tm.version().await
   }
@@ -93,7 +108,10 @@ impl<K, V, SP: AsyncSpawner> OptimisticDb<K, V, SP> {
 impl<K, V, SP: AsyncSpawner, S> OptimisticDb<K, V, SP, S> {
   /// Creates a new `OptimisticDb` with the given hasher.
   #[inline]
-  pub async fn with_hasher(hasher: S) -> Self {
+  pub async fn with_hasher(hasher: S) -> Self
+  where
+    S: Clone,
+  {
     let inner = Arc::new(Inner::<_, _, SP, _>::new(core::any::type_name::<Self>(), hasher).await);
     Self { inner }
   }
@@ -104,6 +122,96 @@ impl<K, V, SP: AsyncSpawner, S> OptimisticDb<K, V, SP, S> {
     self.inner.version().await
   }
 
+  /// Seeds the current version of the database, e.g. with the last committed version
+  /// recovered from a WAL.
+  ///
+  /// Returns [`OracleError::ActiveTransactions`] if any read or write transaction is active, and
+  /// [`OracleError::InvalidVersion`] if `version` is not greater than the current version.
+  #[inline]
+  pub async fn set_initial_version(&self, version: u64) -> Result<(), OracleError> {
+    self.inner.tm.set_initial_version(version).await
+  }
+
+  /// Closes the database gracefully, no write transaction can be created afterwards,
+  /// and waits until all the active write transactions are committed or discarded.
+  ///
+  /// Returns [`OracleError::Timeout`] if the `grace` future completes first.
+  ///
+  /// [`try_write`](OptimisticDb::try_write) returns [`TransactionError::Closed`] once the
+  /// database is closed.
+  #[inline]
+  pub async fn close_gracefully(
+    &self,
+    grace: impl core::future::Future<Output = ()>,
+  ) -> Result<(), OracleError> {
+    self.inner.tm.close_gracefully(grace).await
+  }
+
+  /// Locks the key in the lock table of the database, waits until the key is unlocked if
+  /// it is locked by others, e.g. by a [`PessimisticWriteTransaction`].
+  ///
+  /// The key is unlocked when the returned guard is dropped.
+  #[inline]
+  pub async fn lock_key(&self, key: &K) -> LockGuard<K, S>
+  where
+    K: Hash + Eq + Clone,
+    S: BuildHasher,
+  {
+    self.inner.locks.lock(key).await
+  }
+
+  /// Returns the number of write transactions which are not committed or discarded yet.
+  #[inline]
+  pub fn active_transaction_count(&self) -> u64 {
+    self.inner.tm.active_transaction_count()
+  }
+
+  /// Returns the maximum read version of the transactions which are not done yet,
+  /// or 0 if there is none.
+  #[inline]
+  pub fn max_active_read_version(&self) -> u64 {
+    self.inner.tm.max_active_read_version()
+  }
+
+  /// Returns the minimum read version of the transactions which are not done yet.
+  #[inline]
+  pub fn min_active_read_version(&self) -> Option<u64> {
+    self.inner.tm.min_active_read_version()
+  }
+
+  /// Sets a callback invoked with the gap between the commit version and the minimum active
+  /// read version on every commit, once the gap exceeds `max_gap`.
+  #[inline]
+  pub fn set_version_gap_warning_threshold(
+    &self,
+    max_gap: u64,
+    warn_fn: impl Fn(u64) + Send + Sync + 'static,
+  ) {
+    self
+      .inner
+      .tm
+      .set_version_gap_warning_threshold(max_gap, warn_fn);
+  }
+
+  /// Returns a snapshot of the aggregate transaction counters of the database.
+  #[inline]
+  pub fn statistics(&self) -> TransactionStats {
+    self.inner.tm.statistics()
+  }
+
+  /// Returns an iterator over all the committed versions of all keys in `[from_ts, to_ts]`,
+  /// in ascending order of key, and descending order of version for the same key.
+  ///
+  /// Unlike [`ReadTransaction::iter`], which only yields the latest version of each key,
+  /// the removed versions are also yielded. This is useful for compaction tools.
+  #[inline]
+  pub fn iter_versions(&self, from_ts: u64, to_ts: u64) -> VersionIter<'_, K, V>
+  where
+    K: Ord,
+  {
+    self.inner.map.iter_versions(from_ts, to_ts)
+  }
+
   /// Create a read transaction.
   #[inline]
   pub async fn read(&self) -> ReadTransaction<K, V, OptimisticDb<K, V, SP, S>, HashCm<K, S>, SP> {
@@ -118,15 +226,58 @@ where
   SP: AsyncSpawner,
 {
   /// Create a write transaction.
+  ///
+  /// # Panics
+  /// - If the database is closed by [`close_gracefully`](OptimisticDb::close_gracefully),
+  ///   use [`try_write`](OptimisticDb::try_write) to handle it.
   #[inline]
   pub async fn write(&self) -> OptimisticTransaction<K, V, SP, S> {
+    self.try_write().await.expect("the database is closed")
+  }
+
+  /// Create a write transaction, returns [`TransactionError::Closed`] if the database is
+  /// closed by [`close_gracefully`](OptimisticDb::close_gracefully).
+  #[inline]
+  pub async fn try_write(
+    &self,
+  ) -> Result<OptimisticTransaction<K, V, SP, S>, TransactionError<Infallible, Infallible>> {
     OptimisticTransaction::new(self.clone(), None).await
   }
 
-  /// Create a write transaction with the given capacity hint.
+  /// Create a write transaction which locks the keys it reads, see
+  /// [`PessimisticWriteTransaction`], returns [`TransactionError::Closed`] if the database is
+  /// closed by [`close_gracefully`](OptimisticDb::close_gracefully).
+  #[inline]
+  pub async fn write_pessimistic(
+    &self,
+  ) -> Result<PessimisticWriteTransaction<K, V, SP, S>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    self
+      .try_write()
+      .await
+      .map(|txn| PessimisticWriteTransaction::new(txn, self.inner.locks.clone()))
+  }
+
+  /// Create a write transaction with the given capacity hint, panics like
+  /// [`write`](OptimisticDb::write) if the database is closed.
   #[inline]
   pub async fn write_with_capacity(&self, capacity: usize) -> OptimisticTransaction<K, V, SP, S> {
-    OptimisticTransaction::new(self.clone(), Some(capacity)).await
+    OptimisticTransaction::new(self.clone(), Some(capacity))
+      .await
+      .expect("the database is closed")
+  }
+
+  /// Returns the fingerprint the write transactions of the database use for the key,
+  /// see [`OptimisticTransaction::insert_batch_raw`].
+  #[inline]
+  pub fn fingerprint<Q>(&self, key: &Q) -> u64
+  where
+    K: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.inner.hasher.hash_one(key)
   }
 }
 
@@ -141,4 +292,103 @@ where
   pub fn compact(&self) {
     self.inner.map.compact(self.inner.tm.discard_hint());
   }
+
+  /// Removes the versions older than `version` which are not visible to the reads at
+  /// `version` or later, and the keys removed before `version`.
+  ///
+  /// Returns [`TransactionError::InvalidVersion`] if `version` is larger than the
+  /// [minimum active read version](OptimisticDb::min_active_read_version), or the current
+  /// version if there is no active transaction, as the versions may still be read.
+  /// Nothing is compacted if `version` is not larger than the one of the last compaction,
+  /// or another compaction is running.
+  pub async fn compact_below_version(
+    &self,
+    version: u64,
+  ) -> Result<CompactionStats, TransactionError<Infallible, Infallible>> {
+    let frontier = match self.inner.tm.min_active_read_version() {
+      Some(frontier) => frontier,
+      None => self.inner.version().await,
+    };
+    if version > frontier {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    Ok(self.inner.map.compact(version))
+  }
+
+  /// Imports the key-value pairs into the database at a single new version, bypassing
+  /// the transaction layer, returns the version of the imported data.
+  ///
+  /// This is for bulk loading when initializing the database only, it is **NOT** safe to
+  /// call concurrently with live write transactions, as they will not conflict with the
+  /// imported data.
+  ///
+  /// Returns [`OracleError::Overflow`] if the new version would overflow.
+  pub async fn import_snapshot(
+    &self,
+    entries: impl IntoIterator<Item = (K, V)>,
+  ) -> Result<u64, OracleError> {
+    let map = &self.inner.map;
+    self
+      .inner
+      .tm
+      .import::<_, _, OracleError>(|version| async move {
+        map.apply(
+          entries
+            .into_iter()
+            .map(|(key, value)| Entry::new(version, EntryData::Insert { key, value }))
+            .collect(),
+        );
+        Ok(())
+      })
+      .await
+  }
+}
+
+impl<K, V, SP, S> OptimisticDb<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Send + Sync + 'static,
+  V: Send + Sync + 'static,
+  S: BuildHasher + Clone + Send + Sync + 'static,
+  SP: AsyncSpawner,
+{
+  /// Writes the key-value pairs into the database by a series of write transactions,
+  /// each of them holds at most `chunk_size` entries and is committed before the next
+  /// one is opened, returns the commit versions of the transactions.
+  ///
+  /// The writes are **NOT** atomic, if an error occurs, the transactions committed before
+  /// are kept in the database. Returns [`TransactionError::Closed`] if the database is closed
+  /// by [`close_gracefully`](OptimisticDb::close_gracefully).
+  ///
+  /// # Panics
+  /// - If `chunk_size` is zero.
+  pub async fn write_batch_bounded(
+    &self,
+    items: impl IntoIterator<Item = (K, V)>,
+    chunk_size: usize,
+  ) -> Result<Vec<u64>, WtmError<Infallible, Infallible, Infallible>> {
+    assert!(chunk_size > 0, "chunk_size must be greater than zero");
+
+    let mut versions = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+      let mut txn = OptimisticTransaction::new(self.clone(), Some(chunk_size)).await?;
+      for (key, value) in items.by_ref().take(chunk_size) {
+        txn.insert(key, value)?;
+      }
+
+      let db = self.clone();
+      let version = txn
+        .wtm
+        .commit(|ents| async move {
+          let version = ents.first().map(|ent| ent.version());
+          db.inner.map.apply(ents);
+          Ok(version)
+        })
+        .await?;
+      versions.extend(version);
+    }
+
+    Ok(versions)
+  }
 }
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, sync::Mutex as StdMutex};
+
+use futures::lock::{Mutex, OwnedMutexGuard};
+
+use super::*;
+
+type Locks<K, S> = Arc<StdMutex<HashMap<K, Arc<Mutex<()>>, S>>>;
+
+/// A table of per-key locks, used to lock keys pessimistically on top of the optimistic
+/// concurrency control.
+///
+/// A key is only kept in the table while it is locked or waited for.
+pub struct LockTable<K, S = RandomState> {
+  locks: Locks<K, S>,
+}
+
+impl<K, S> Clone for LockTable<K, S> {
+  #[inline]
+  fn clone(&self) -> Self {
+    Self {
+      locks: self.locks.clone(),
+    }
+  }
+}
+
+impl<K, S: Default> Default for LockTable<K, S> {
+  #[inline]
+  fn default() -> Self {
+    Self::with_hasher(S::default())
+  }
+}
+
+impl<K> LockTable<K> {
+  /// Creates a new empty `LockTable`.
+  #[inline]
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl<K, S> LockTable<K, S> {
+  /// Creates a new empty `LockTable` with the given hasher.
+  #[inline]
+  pub fn with_hasher(hasher: S) -> Self {
+    Self {
+      locks: Arc::new(StdMutex::new(HashMap::with_hasher(hasher))),
+    }
+  }
+
+  /// Returns the number of keys which are locked or waited for.
+  #[inline]
+  pub fn len(&self) -> usize {
+    self.locks.lock().unwrap().len()
+  }
+
+  /// Returns `true` if no key is locked or waited for.
+  #[inline]
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<K, S> LockTable<K, S>
+where
+  K: Hash + Eq + Clone,
+  S: BuildHasher,
+{
+  /// Locks the key, waits until the key is unlocked if it is locked by others.
+  ///
+  /// The key is unlocked when the returned guard is dropped.
+  pub async fn lock(&self, key: &K) -> LockGuard<K, S> {
+    let lock = self
+      .locks
+      .lock()
+      .unwrap()
+      .entry(key.clone())
+      .or_default()
+      .clone();
+
+    let guard = lock.clone().lock_owned().await;
+    LockGuard {
+      key: key.clone(),
+      lock,
+      guard: Some(guard),
+      locks: self.locks.clone(),
+    }
+  }
+}
+
+/// A guard of a key locked by the [`LockTable`], the key is unlocked when the guard is dropped.
+pub struct LockGuard<K, S = RandomState>
+where
+  K: Hash + Eq,
+  S: BuildHasher,
+{
+  key: K,
+  lock: Arc<Mutex<()>>,
+  guard: Option<OwnedMutexGuard<()>>,
+  locks: Locks<K, S>,
+}
+
+impl<K, S> LockGuard<K, S>
+where
+  K: Hash + Eq,
+  S: BuildHasher,
+{
+  /// Returns the locked key.
+  #[inline]
+  pub fn key(&self) -> &K {
+    &self.key
+  }
+}
+
+impl<K, S> Drop for LockGuard<K, S>
+where
+  K: Hash + Eq,
+  S: BuildHasher,
+{
+  fn drop(&mut self) {
+    self.guard.take();
+
+    // The lock is only cloned out of the table while the table is locked, so if the table
+    // and this guard hold the last references, no one else is holding or waiting for it.
+    let mut locks = self.locks.lock().unwrap();
+    if locks
+      .get(&self.key)
+      .is_some_and(|lock| Arc::ptr_eq(lock, &self.lock) && Arc::strong_count(lock) == 2)
+    {
+      locks.remove(&self.key);
+    }
+  }
+}
+
+/// A write transaction over the [`OptimisticDb`] which locks the keys it reads.
+///
+/// The keys are locked on [`get`](PessimisticWriteTransaction::get) and unlocked when the
+/// transaction is committed or dropped, so the pessimistic transactions reading the same key
+/// run one after another. The transaction is still committed through the optimistic
+/// concurrency control, the snapshot is taken when the first key is locked, a transaction
+/// which committed a key locked later after the snapshot but before the key was locked
+/// still makes this transaction conflict.
+///
+/// Pessimistic transactions locking the same keys in different orders may deadlock.
+pub struct PessimisticWriteTransaction<K, V, SP, S = RandomState>
+where
+  K: Hash + Eq,
+  S: BuildHasher,
+  SP: AsyncSpawner,
+{
+  txn: OptimisticTransaction<K, V, SP, S>,
+  locks: LockTable<K, S>,
+  guards: Vec<LockGuard<K, S>>,
+}
+
+impl<K, V, SP, S> PessimisticWriteTransaction<K, V, SP, S>
+where
+  K: Hash + Eq,
+  S: BuildHasher,
+  SP: AsyncSpawner,
+{
+  #[inline]
+  pub(super) fn new(txn: OptimisticTransaction<K, V, SP, S>, locks: LockTable<K, S>) -> Self {
+    Self {
+      txn,
+      locks,
+      guards: Vec::new(),
+    }
+  }
+}
+
+impl<K, V, SP, S> PessimisticWriteTransaction<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Clone,
+  V: 'static,
+  S: BuildHasher + Clone,
+  SP: AsyncSpawner,
+{
+  /// Locks the key until the transaction is committed or dropped, does nothing if the key
+  /// is already locked by this transaction.
+  pub async fn lock_key(&mut self, key: &K) {
+    if self.guards.iter().any(|guard| guard.key() == key) {
+      return;
+    }
+
+    let guard = self.locks.lock(key).await;
+    self.guards.push(guard);
+  }
+
+  /// Inserts a new key-value pair.
+  #[inline]
+  pub fn insert(
+    &mut self,
+    key: K,
+    value: V,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.txn.insert(key, value)
+  }
+
+  /// Removes a key.
+  #[inline]
+  pub fn remove(&mut self, key: K) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.txn.remove(key)
+  }
+
+  /// Returns the underlying optimistic transaction, the keys accessed through it are not
+  /// locked.
+  #[inline]
+  pub fn transaction(&mut self) -> &mut OptimisticTransaction<K, V, SP, S> {
+    &mut self.txn
+  }
+}
+
+impl<K, V, SP, S> PessimisticWriteTransaction<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Clone + Send + Sync + 'static,
+  V: Send + Sync + 'static,
+  S: BuildHasher + Clone + Send + Sync + 'static,
+  SP: AsyncSpawner,
+{
+  /// Locks the key and gets its value.
+  ///
+  /// If the transaction has not read any key yet, it is re-pinned at the latest version
+  /// after the key is locked, so the value committed by the pessimistic transaction which
+  /// held the lock before is seen instead of making this transaction conflict. Otherwise the
+  /// snapshot is kept, as re-pinning would forget the keys read before.
+  pub async fn get<'a, 'b: 'a>(
+    &'a mut self,
+    key: &'b K,
+  ) -> Result<Option<Ref<'a, K, V>>, TransactionError<Infallible, Infallible>> {
+    self.lock_key(key).await;
+    if self.txn.reads_iter().next().is_none() {
+      self.txn.reset_read_ts_to_latest().await?;
+    }
+    self.txn.get(key)
+  }
+}
+
+impl<K, V, SP, S> PessimisticWriteTransaction<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Clone + Send + Sync + 'static,
+  V: Send + Sync + 'static,
+  S: BuildHasher + Send + Sync + 'static,
+  SP: AsyncSpawner,
+{
+  /// Commits the transaction like [`OptimisticTransaction::commit`], and unlocks all the keys
+  /// locked by the transaction whether the commit succeeds or not.
+  pub async fn commit(&mut self) -> Result<(), WtmError<Infallible, Infallible, Infallible>> {
+    let rst = self.txn.commit().await;
+    self.guards.clear();
+    rst
+  }
+}
@@ -0,0 +1,106 @@
+use std::{
+  future::Future,
+  ops::{Deref, DerefMut},
+};
+
+use super::*;
+
+/// An [`OptimisticTransaction`] carrying the user-defined metadata, e.g. a causality token
+/// or a trace ID, created by [`OptimisticTransaction::with_metadata`].
+///
+/// The transaction methods are reachable through [`Deref`] and [`DerefMut`].
+pub struct OptimisticTransactionWithMetadata<K, V, SP, S, M>
+where
+  SP: AsyncSpawner,
+{
+  txn: OptimisticTransaction<K, V, SP, S>,
+  metadata: M,
+}
+
+impl<K, V, SP, S> OptimisticTransaction<K, V, SP, S>
+where
+  SP: AsyncSpawner,
+{
+  /// Attaches the metadata to the transaction.
+  #[inline]
+  pub fn with_metadata<M: Send + 'static>(
+    self,
+    metadata: M,
+  ) -> OptimisticTransactionWithMetadata<K, V, SP, S, M> {
+    OptimisticTransactionWithMetadata {
+      txn: self,
+      metadata,
+    }
+  }
+}
+
+impl<K, V, SP, S, M> OptimisticTransactionWithMetadata<K, V, SP, S, M>
+where
+  SP: AsyncSpawner,
+{
+  /// Returns the metadata of the transaction.
+  #[inline]
+  pub fn metadata(&self) -> &M {
+    &self.metadata
+  }
+
+  /// Returns the mutable metadata of the transaction.
+  #[inline]
+  pub fn metadata_mut(&mut self) -> &mut M {
+    &mut self.metadata
+  }
+
+  /// Consumes the wrapper and returns the transaction and its metadata.
+  #[inline]
+  pub fn into_parts(self) -> (OptimisticTransaction<K, V, SP, S>, M) {
+    (self.txn, self.metadata)
+  }
+}
+
+impl<K, V, SP, S, M> Deref for OptimisticTransactionWithMetadata<K, V, SP, S, M>
+where
+  SP: AsyncSpawner,
+{
+  type Target = OptimisticTransaction<K, V, SP, S>;
+
+  #[inline]
+  fn deref(&self) -> &Self::Target {
+    &self.txn
+  }
+}
+
+impl<K, V, SP, S, M> DerefMut for OptimisticTransactionWithMetadata<K, V, SP, S, M>
+where
+  SP: AsyncSpawner,
+{
+  #[inline]
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.txn
+  }
+}
+
+impl<K, V, SP, S, M> OptimisticTransactionWithMetadata<K, V, SP, S, M>
+where
+  K: Ord + Hash + Eq + Send + Sync + 'static,
+  V: Send + Sync + 'static,
+  S: BuildHasher + Send + Sync + 'static,
+  SP: AsyncSpawner,
+  M: Send + 'static,
+{
+  /// Acts like [`OptimisticTransaction::commit_with_task`], but the metadata is moved into
+  /// the callback along with the result of the commit.
+  pub async fn commit_with_task<Fut, E, R>(
+    self,
+    callback: impl FnOnce(Result<(), E>, M) -> Fut + Send + 'static,
+  ) -> Result<SP::JoinHandle<R>, WtmError<Infallible, Infallible, E>>
+  where
+    Fut: Future<Output = R> + Send + 'static,
+    E: std::error::Error + Send,
+    R: Send + 'static,
+  {
+    let Self { mut txn, metadata } = self;
+    txn
+      .commit_with_task(move |rst| callback(rst, metadata))
+      .await
+  }
+}
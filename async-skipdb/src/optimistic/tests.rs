@@ -2,11 +2,12 @@
 
 use std::{
   future::Future,
+  ops::Bound,
   sync::atomic::{AtomicU32, Ordering},
   time::Duration,
 };
 
-use async_txn::error::WtmError;
+use async_txn::error::{OracleError, WtmError};
 use futures::{stream::FuturesUnordered, FutureExt, StreamExt};
 use rand::{rngs::OsRng, Rng};
 use skipdb_core::rev_range::WriteTransactionRevRange;
@@ -1195,3 +1196,2850 @@ async fn range_async_std() {
 fn range_smol() {
   smol::block_on(range_in::<SmolSpawner>());
 }
+
+async fn get_multi_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  for i in 1..=3 {
+    let mut tx = db.write().await;
+    tx.insert(0, i).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  {
+    let mut tx = db.write().await;
+    tx.remove(0).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  let versions = tx
+    .get_multi_version(&0, 2, 4)
+    .unwrap()
+    .map(|ent| (ent.version(), ent.value().map(|v| *v)))
+    .collect::<Vec<_>>();
+  assert_eq!(versions, vec![(2, Some(2)), (3, Some(3)), (4, None)]);
+  assert_eq!(tx.get_multi_version(&1, 0, 4).unwrap().count(), 0);
+  assert!(matches!(
+    tx.get_multi_version(&0, 3, 2),
+    Err(TransactionError::InvalidVersion)
+  ));
+  assert!(matches!(
+    tx.get_multi_version(&0, 0, 5),
+    Err(TransactionError::InvalidVersion)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_multi_version_tokio() {
+  get_multi_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_multi_version_async_std() {
+  get_multi_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_multi_version_smol() {
+  smol::block_on(get_multi_version_in::<SmolSpawner>());
+}
+
+async fn iter_versions_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  for i in 1..=3 {
+    let mut tx = db.write().await;
+    tx.insert(0, i).unwrap();
+    tx.insert(i, i).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  {
+    let mut tx = db.write().await;
+    tx.remove(0).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let versions = db
+    .iter_versions(2, 4)
+    .map(|ent| (*ent.key(), ent.version(), ent.value().map(|v| *v)))
+    .collect::<Vec<_>>();
+  assert_eq!(
+    versions,
+    vec![
+      (0, 4, None),
+      (0, 3, Some(3)),
+      (0, 2, Some(2)),
+      (2, 2, Some(2)),
+      (3, 3, Some(3)),
+    ]
+  );
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn iter_versions_tokio() {
+  iter_versions_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn iter_versions_async_std() {
+  iter_versions_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn iter_versions_smol() {
+  smol::block_on(iter_versions_in::<SmolSpawner>());
+}
+
+async fn replace_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  {
+    let mut tx = db.write().await;
+    assert_eq!(tx.replace(1, 1).unwrap(), None);
+    assert_eq!(tx.replace(1, 2).unwrap(), Some(1));
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.replace(1, 3).unwrap(), Some(2));
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 3);
+
+  // the key is marked as read, so a concurrent write conflicts.
+  let mut tx2 = db.write().await;
+  tx2.insert(1, 4).unwrap();
+  tx2.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn replace_tokio() {
+  replace_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn replace_async_std() {
+  replace_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn replace_smol() {
+  smol::block_on(replace_in::<SmolSpawner>());
+}
+
+async fn get_or_default_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.get_or_else(&2, || 2).unwrap(), 2);
+  assert!(!tx.contains_key(&2).unwrap());
+  assert_eq!(tx.get_or_else(&1, || 2).unwrap(), 1);
+
+  assert_eq!(*tx.get_or_default(1).unwrap().value(), 1);
+  {
+    let ent = tx.get_or_default(2).unwrap();
+    assert!(!ent.is_committed());
+    assert_eq!(*ent.value(), 0);
+  }
+  assert!(tx.contains_key(&2).unwrap());
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&2).unwrap().value(), 0);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_or_default_tokio() {
+  get_or_default_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_or_default_async_std() {
+  get_or_default_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_or_default_smol() {
+  smol::block_on(get_or_default_in::<SmolSpawner>());
+}
+
+async fn pin_value_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let pinned = {
+    let mut tx = db.write().await;
+    assert!(tx.pin_value(&2).unwrap().is_none());
+    let pinned = tx.pin_value(&1).unwrap().unwrap();
+    tx.insert(1, 2).unwrap();
+    tx.commit().await.unwrap();
+    pinned
+  };
+  assert_eq!(*pinned, 1);
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 2);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn pin_value_tokio() {
+  pin_value_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn pin_value_async_std() {
+  pin_value_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn pin_value_smol() {
+  smol::block_on(pin_value_in::<SmolSpawner>());
+}
+
+async fn conflict_keys_iter_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.conflict_keys_iter().unwrap().count(), 0);
+  assert_eq!(tx.reads_iter().count(), 0);
+
+  tx.get(&1).unwrap();
+  tx.insert(2, 2).unwrap();
+  tx.insert(3, 3).unwrap();
+  assert_eq!(tx.reads_iter().count(), 1);
+  assert_eq!(tx.conflict_keys_iter().unwrap().count(), 2);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn conflict_keys_iter_tokio() {
+  conflict_keys_iter_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn conflict_keys_iter_async_std() {
+  conflict_keys_iter_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn conflict_keys_iter_smol() {
+  smol::block_on(conflict_keys_iter_in::<SmolSpawner>());
+}
+
+async fn absorb_discard_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.insert(2, 2).unwrap();
+  let mut tx = OptimisticTransaction::absorb_discard(tx, 0).await.unwrap();
+  assert_eq!(*tx.get(&2).unwrap().unwrap().value(), 2);
+
+  // the writes are still tracked for conflict detection.
+  let mut reader = db.write().await;
+  reader.get(&2).unwrap();
+  reader.insert(3, 3).unwrap();
+
+  tx.commit().await.unwrap();
+  assert!(matches!(
+    reader.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+
+  let mut stale = db.write().await;
+  stale.insert(4, 4).unwrap();
+  {
+    let mut tx = db.write().await;
+    tx.insert(5, 5).unwrap();
+    tx.commit().await.unwrap();
+  }
+  assert!(matches!(
+    OptimisticTransaction::absorb_discard(stale, 0).await,
+    Err(TransactionError::StaleTransaction { lag: 1 })
+  ));
+  assert_eq!(db.active_transaction_count(), 0);
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+  assert!(rx.get(&4).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn absorb_discard_tokio() {
+  absorb_discard_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn absorb_discard_async_std() {
+  absorb_discard_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn absorb_discard_smol() {
+  smol::block_on(absorb_discard_in::<SmolSpawner>());
+}
+
+async fn entries_since_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  for i in 1..=3 {
+    let mut tx = db.write().await;
+    tx.insert(i, i).unwrap();
+    tx.commit().await.unwrap();
+  }
+  let since = db.version().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 10).unwrap();
+    tx.remove(2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let rx = db.read().await;
+  let changes = rx
+    .entries_since_version(since)
+    .unwrap()
+    .map(|ver| (*ver.key(), ver.value().map(|v| *v)))
+    .collect::<Vec<_>>();
+  assert_eq!(changes, vec![(1, Some(10)), (2, None)]);
+  assert_eq!(rx.entries_since_version(rx.version()).unwrap().count(), 0);
+  drop(rx);
+
+  // versions before the discard version may be compacted.
+  db.compact();
+  let rx = db.read().await;
+  assert!(matches!(
+    rx.entries_since_version(0),
+    Err(TransactionError::InvalidVersion)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn entries_since_version_tokio() {
+  entries_since_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn entries_since_version_async_std() {
+  entries_since_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn entries_since_version_smol() {
+  smol::block_on(entries_since_version_in::<SmolSpawner>());
+}
+
+async fn rename_key_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert!(!tx.rename_key(3, 4).unwrap());
+  assert!(!tx.contains_key(&4).unwrap());
+  assert!(tx.rename_key(1, 1).unwrap());
+  assert!(tx.rename_key(1, 2).unwrap());
+  assert!(!tx.contains_key(&1).unwrap());
+  assert_eq!(*tx.get(&2).unwrap().unwrap().value(), 1);
+
+  // pending values can be renamed as well.
+  tx.insert(5, 5).unwrap();
+  assert!(tx.rename_key(5, 6).unwrap());
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert!(rx.get(&1).is_none());
+  assert!(rx.get(&5).is_none());
+  assert_eq!(*rx.get(&2).unwrap().value(), 1);
+  assert_eq!(*rx.get(&6).unwrap().value(), 5);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn rename_key_tokio() {
+  rename_key_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn rename_key_async_std() {
+  rename_key_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn rename_key_smol() {
+  smol::block_on(rename_key_in::<SmolSpawner>());
+}
+
+async fn import_snapshot_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let version = db.import_snapshot((0..100).map(|i| (i, i))).await.unwrap();
+  assert_eq!(db.version().await, version);
+
+  let rx = db.read().await;
+  assert_eq!(rx.iter().count(), 100);
+  assert!(rx.iter().all(|ent| ent.version() == version));
+
+  let mut tx = db.write().await;
+  tx.insert(0, 1).unwrap();
+  tx.commit().await.unwrap();
+  assert!(db.version().await > version);
+
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  db.set_initial_version(u64::MAX - 1).await.unwrap();
+  assert_eq!(
+    db.import_snapshot([(0, 0)]).await.unwrap_err(),
+    OracleError::Overflow
+  );
+  assert_eq!(db.version().await, u64::MAX - 1);
+  assert!(db.read().await.get(&0).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn import_snapshot_tokio() {
+  import_snapshot_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn import_snapshot_async_std() {
+  import_snapshot_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn import_snapshot_smol() {
+  smol::block_on(import_snapshot_in::<SmolSpawner>());
+}
+
+async fn contains_range_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(5, 5).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert!(tx.contains_range(0..2).unwrap());
+  assert!(!tx.contains_range(2..5).unwrap());
+  tx.remove(1).unwrap();
+  assert!(!tx.contains_range(0..2).unwrap());
+  tx.insert(3, 3).unwrap();
+  assert!(tx.contains_range(2..=4).unwrap());
+
+  // a concurrent insert into the range conflicts.
+  let mut other = db.write().await;
+  other.insert(7, 7).unwrap();
+  other.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn contains_range_tokio() {
+  contains_range_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn contains_range_async_std() {
+  contains_range_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn contains_range_smol() {
+  smol::block_on(contains_range_in::<SmolSpawner>());
+}
+
+async fn map_values_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(2, 2).unwrap();
+  tx.insert(3, 3).unwrap();
+  tx.remove(1).unwrap();
+  tx.map_values(|k, v| k * 10 + v).unwrap();
+  assert_eq!(*tx.get(&2).unwrap().unwrap().value(), 22);
+  assert_eq!(*tx.get(&3).unwrap().unwrap().value(), 33);
+  assert!(!tx.contains_key(&1).unwrap());
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert!(rx.get(&1).is_none());
+  assert_eq!(*rx.get(&2).unwrap().value(), 22);
+  assert_eq!(*rx.get(&3).unwrap().value(), 33);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn map_values_tokio() {
+  map_values_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn map_values_async_std() {
+  map_values_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn map_values_smol() {
+  smol::block_on(map_values_in::<SmolSpawner>());
+}
+
+async fn insert_batch_raw_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut txn1 = db.write().await;
+  assert!(txn1.get(&1).unwrap().is_none());
+  txn1.insert(0, 0).unwrap();
+
+  let mut txn2 = db.write().await;
+  txn2
+    .insert_batch_raw((1..3).map(|k| (k, k, db.fingerprint(&k))))
+    .unwrap();
+  txn2.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 1);
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+
+  assert!(matches!(
+    txn1.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn insert_batch_raw_tokio() {
+  insert_batch_raw_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn insert_batch_raw_async_std() {
+  insert_batch_raw_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn insert_batch_raw_smol() {
+  smol::block_on(insert_batch_raw_in::<SmolSpawner>());
+}
+
+async fn commit_at_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.insert(1, 1).unwrap();
+  tx.commit_at_version(10).await.unwrap();
+  assert_eq!(db.version().await, 10);
+
+  let rx = db.read().await;
+  assert_eq!(rx.get(&1).unwrap().version(), 10);
+  drop(rx);
+
+  // not greater than the latest committed version.
+  let mut txn1 = db.write().await;
+  txn1.insert(2, 2).unwrap();
+  assert!(matches!(
+    txn1.commit_at_version(10).await.unwrap_err(),
+    WtmError::Transaction(TransactionError::InvalidVersion)
+  ));
+
+  // conflicts are still detected.
+  let mut txn1 = db.write().await;
+  let mut txn2 = db.write().await;
+  assert_eq!(*txn1.get(&1).unwrap().unwrap().value(), 1);
+  txn1.insert(3, 3).unwrap();
+  txn2.insert(1, 2).unwrap();
+  txn2.commit_at_version(20).await.unwrap();
+  assert!(matches!(
+    txn1.commit_at_version(30).await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+
+  // the next version would overflow.
+  let mut txn1 = db.write().await;
+  txn1.insert(5, 5).unwrap();
+  assert!(matches!(
+    txn1.commit_at_version(u64::MAX).await.unwrap_err(),
+    WtmError::Transaction(TransactionError::InvalidVersion)
+  ));
+  assert_eq!(db.version().await, 20);
+
+  let mut tx = db.write().await;
+  tx.insert(4, 4).unwrap();
+  tx.commit().await.unwrap();
+  assert_eq!(db.version().await, 21);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn commit_at_version_tokio() {
+  commit_at_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn commit_at_version_async_std() {
+  commit_at_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn commit_at_version_smol() {
+  smol::block_on(commit_at_version_in::<SmolSpawner>());
+}
+
+async fn get_range_count_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    for i in 0..10 {
+      tx.insert(i, i).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+
+  let mut txn1 = db.write().await;
+  assert_eq!(txn1.get_range_count(..).unwrap(), 10);
+  assert_eq!(txn1.get_range_count(2..5).unwrap(), 3);
+  // insert a new key, overwrite and remove existing keys.
+  txn1.insert(20, 20).unwrap();
+  txn1.insert(3, 30).unwrap();
+  txn1.remove(4).unwrap();
+  txn1.remove(15).unwrap();
+  assert_eq!(txn1.get_range_count(2..5).unwrap(), 2);
+  assert_eq!(txn1.get_range_count(2..).unwrap(), 8);
+
+  let mut txn2 = db.write().await;
+  txn2.insert(100, 100).unwrap();
+  txn2.commit().await.unwrap();
+
+  assert!(matches!(
+    txn1.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_range_count_tokio() {
+  get_range_count_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_range_count_async_std() {
+  get_range_count_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_range_count_smol() {
+  smol::block_on(get_range_count_in::<SmolSpawner>());
+}
+
+async fn conflict_keys_limit_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.set_conflict_keys_limit(2);
+  tx.insert(1, 1).unwrap();
+  tx.remove(2).unwrap();
+  assert!(matches!(
+    tx.insert(3, 3).unwrap_err(),
+    TransactionError::TooManyConflictKeys
+  ));
+  // the keys already written can still be overwritten.
+  tx.insert(2, 2).unwrap();
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 1);
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+  assert!(rx.get(&3).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn conflict_keys_limit_tokio() {
+  conflict_keys_limit_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn conflict_keys_limit_async_std() {
+  conflict_keys_limit_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn conflict_keys_limit_smol() {
+  smol::block_on(conflict_keys_limit_in::<SmolSpawner>());
+}
+
+async fn into_entries_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.insert(1, 1).unwrap();
+  tx.insert(2, 2).unwrap();
+  tx.remove(3).unwrap();
+  let entries = tx.into_entries().unwrap();
+  assert_eq!(entries.len(), 3);
+  assert!(entries.iter().all(|ent| ent.version() == 0));
+  assert_eq!(
+    entries
+      .iter()
+      .filter(|ent| matches!(ent.data(), EntryData::Insert { .. }))
+      .count(),
+    2
+  );
+  assert_eq!(db.active_transaction_count(), 0);
+
+  // nothing is applied to the database.
+  assert_eq!(db.version().await, 0);
+  let rx = db.read().await;
+  assert!(rx.get(&1).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn into_entries_tokio() {
+  into_entries_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn into_entries_async_std() {
+  into_entries_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn into_entries_smol() {
+  smol::block_on(into_entries_in::<SmolSpawner>());
+}
+
+async fn remove_pending_range_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(3, 3).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  for i in 0..10 {
+    tx.insert(i, i * 10).unwrap();
+  }
+  tx.remove(20).unwrap();
+  assert_eq!(tx.remove_pending_range(2..5).unwrap(), 3);
+  assert_eq!(tx.remove_pending_range(8..).unwrap(), 3);
+  assert_eq!(tx.remove_pending_range(8..).unwrap(), 0);
+  // the committed value is visible again.
+  assert_eq!(*tx.get(&3).unwrap().unwrap().value(), 3);
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  let keys = rx.iter().map(|ent| *ent.key()).collect::<Vec<_>>();
+  assert_eq!(keys, [0, 1, 3, 5, 6, 7]);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn remove_pending_range_tokio() {
+  remove_pending_range_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn remove_pending_range_async_std() {
+  remove_pending_range_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn remove_pending_range_smol() {
+  smol::block_on(remove_pending_range_in::<SmolSpawner>());
+}
+
+async fn get_or_create_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  {
+    let (ent, created) = tx.get_or_create(1, || unreachable!()).unwrap();
+    assert_eq!(*ent.value(), 1);
+    assert!(!created);
+  }
+
+  {
+    let (ent, created) = tx.get_or_create(2, || 20).unwrap();
+    assert_eq!(*ent.value(), 20);
+    assert!(created);
+  }
+
+  {
+    let (ent, created) = tx.get_or_create(2, || unreachable!()).unwrap();
+    assert_eq!(*ent.value(), 20);
+    assert!(!created);
+  }
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&2).unwrap().value(), 20);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_or_create_tokio() {
+  get_or_create_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_or_create_async_std() {
+  get_or_create_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_or_create_smol() {
+  smol::block_on(get_or_create_in::<SmolSpawner>());
+}
+
+async fn get_committed_only_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut txn1 = db.write().await;
+  txn1.insert(1, 10).unwrap();
+  txn1.insert(2, 20).unwrap();
+  assert_eq!(*txn1.get_committed_only(&1).unwrap().unwrap().value(), 1);
+  assert!(txn1.get_committed_only(&2).unwrap().is_none());
+  assert_eq!(*txn1.get(&1).unwrap().unwrap().value(), 10);
+
+  // the key is marked as read.
+  let mut txn2 = db.write().await;
+  txn2.insert(2, 2).unwrap();
+  txn2.commit().await.unwrap();
+  assert!(matches!(
+    txn1.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_committed_only_tokio() {
+  get_committed_only_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_committed_only_async_std() {
+  get_committed_only_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_committed_only_smol() {
+  smol::block_on(get_committed_only_in::<SmolSpawner>());
+}
+
+async fn compare_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut txn1 = db.write().await;
+  assert!(txn1.compare_version(&1, 1).unwrap());
+  assert!(!txn1.compare_version(&1, 0).unwrap());
+  assert!(!txn1.compare_version(&2, 0).unwrap());
+  txn1.insert(1, 10).unwrap();
+
+  let mut txn2 = db.write().await;
+  txn2.insert(1, 2).unwrap();
+  txn2.commit().await.unwrap();
+  assert!(matches!(
+    txn1.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+
+  let mut tx = db.write().await;
+  assert!(!tx.compare_version(&1, 1).unwrap());
+  assert!(tx.compare_version(&1, 2).unwrap());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn compare_version_tokio() {
+  compare_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn compare_version_async_std() {
+  compare_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn compare_version_smol() {
+  smol::block_on(compare_version_in::<SmolSpawner>());
+}
+
+async fn atomic_swap_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 10).unwrap();
+    tx.insert(2, 20).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.atomic_swap(1, 2).unwrap(), (true, true));
+  assert_eq!(tx.atomic_swap(2, 3).unwrap(), (true, false));
+  assert_eq!(tx.atomic_swap(1, 1).unwrap(), (true, true));
+  // key 2 has a pending remove now.
+  assert!(tx.get(&2).unwrap().is_none());
+  assert_eq!(tx.atomic_swap(2, 4).unwrap(), (false, false));
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 20);
+  assert!(rx.get(&2).is_none());
+  assert_eq!(*rx.get(&3).unwrap().value(), 10);
+  drop(rx);
+
+  // concurrent swaps of the same keys, only one of them commits.
+  let mut txn1 = db.write().await;
+  let mut txn2 = db.write().await;
+  assert_eq!(txn1.atomic_swap(1, 3).unwrap(), (true, true));
+  assert_eq!(txn2.atomic_swap(3, 1).unwrap(), (true, true));
+  txn1.commit().await.unwrap();
+  assert!(matches!(
+    txn2.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 10);
+  assert_eq!(*rx.get(&3).unwrap().value(), 20);
+  drop(rx);
+
+  // swapping a key with itself writes nothing, but still marks the key as conflict.
+  let mut txn1 = db.write().await;
+  let mut txn2 = db.write().await;
+  assert_eq!(*txn1.get(&1).unwrap().unwrap().value(), 10);
+  txn1.insert(9, 9).unwrap();
+  assert_eq!(txn2.atomic_swap(1, 1).unwrap(), (true, true));
+  assert_eq!(txn2.total_entry_count(), 0);
+  txn2.insert(8, 8).unwrap();
+  txn2.commit().await.unwrap();
+  assert!(matches!(
+    txn1.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+  assert_eq!(*db.read().await.get(&1).unwrap().value(), 10);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn atomic_swap_tokio() {
+  atomic_swap_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn atomic_swap_async_std() {
+  atomic_swap_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn atomic_swap_smol() {
+  smol::block_on(atomic_swap_in::<SmolSpawner>());
+}
+
+async fn get_many_for_update_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(2, 2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut txn1 = db.write().await;
+  txn1.insert(3, 3).unwrap();
+  txn1.remove(2).unwrap();
+  {
+    let values = txn1.get_many_for_update(&[1, 2, 3, 4]).unwrap();
+    let values = values
+      .iter()
+      .map(|ent| ent.as_ref().map(|ent| *ent.value()))
+      .collect::<Vec<_>>();
+    assert_eq!(values, [Some(1), None, Some(3), None]);
+  }
+
+  // a transaction reading one of the keys conflicts with the update.
+  let mut txn2 = db.write().await;
+  assert!(txn2.get(&4).unwrap().is_none());
+  txn2.insert(5, 5).unwrap();
+  txn1.commit().await.unwrap();
+  assert!(matches!(
+    txn2.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_many_for_update_tokio() {
+  get_many_for_update_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_many_for_update_async_std() {
+  get_many_for_update_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_many_for_update_smol() {
+  smol::block_on(get_many_for_update_in::<SmolSpawner>());
+}
+
+async fn pending_entries_drain_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut txn1 = db.write().await;
+  txn1.insert(1, 1).unwrap();
+  txn1.insert(2, 2).unwrap();
+  txn1.remove(3).unwrap();
+  let entries = txn1.pending_entries_drain().unwrap();
+  assert_eq!(entries.len(), 3);
+  assert_eq!(txn1.pending_inserts_count(), 0);
+  assert_eq!(txn1.pending_removes_count(), 0);
+  assert_eq!(txn1.estimate_total_commit_size(), 0);
+  assert!(txn1.pending_entries_drain().unwrap().is_empty());
+
+  // move the entries into another transaction.
+  let mut txn2 = db.write().await;
+  for ent in entries {
+    match ent.into_components().1 {
+      EntryData::Insert { key, value } => txn2.insert(key, value).unwrap(),
+      EntryData::Remove(key) => txn2.remove(key).unwrap(),
+    }
+  }
+  txn2.commit().await.unwrap();
+
+  txn1.insert(4, 4).unwrap();
+  txn1.commit().await.unwrap();
+
+  let rx = db.read().await;
+  let keys = rx.iter().map(|ent| *ent.key()).collect::<Vec<_>>();
+  assert_eq!(keys, [1, 2, 4]);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn pending_entries_drain_tokio() {
+  pending_entries_drain_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn pending_entries_drain_async_std() {
+  pending_entries_drain_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn pending_entries_drain_smol() {
+  smol::block_on(pending_entries_drain_in::<SmolSpawner>());
+}
+
+async fn get_or_insert_with_async_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  {
+    let ent = tx
+      .get_or_insert_with_async(1, || async { unreachable!() })
+      .await
+      .unwrap();
+    assert_eq!(*ent.value(), 1);
+  }
+
+  {
+    let ent = tx
+      .get_or_insert_with_async(2, || async { 20 })
+      .await
+      .unwrap();
+    assert_eq!(*ent.value(), 20);
+  }
+
+  {
+    let ent = tx
+      .get_or_insert_with_async(2, || async { unreachable!() })
+      .await
+      .unwrap();
+    assert_eq!(*ent.value(), 20);
+  }
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&2).unwrap().value(), 20);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_or_insert_with_async_tokio() {
+  get_or_insert_with_async_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_or_insert_with_async_async_std() {
+  get_or_insert_with_async_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_or_insert_with_async_smol() {
+  smol::block_on(get_or_insert_with_async_in::<SmolSpawner>());
+}
+
+async fn get_or_insert_with_async_without_read_own_writes_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.set_read_own_writes(false);
+  tx.remove(1).unwrap();
+  {
+    let ent = tx
+      .get_or_insert_with_async(1, || async { unreachable!() })
+      .await
+      .unwrap();
+    assert_eq!(*ent.value(), 1);
+  }
+
+  {
+    let ent = tx
+      .get_or_insert_with_async(2, || async { 20 })
+      .await
+      .unwrap();
+    assert_eq!(*ent.value(), 20);
+  }
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert!(rx.get(&1).is_none());
+  assert_eq!(*rx.get(&2).unwrap().value(), 20);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_or_insert_with_async_without_read_own_writes_tokio() {
+  get_or_insert_with_async_without_read_own_writes_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_or_insert_with_async_without_read_own_writes_async_std() {
+  get_or_insert_with_async_without_read_own_writes_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_or_insert_with_async_without_read_own_writes_smol() {
+  smol::block_on(get_or_insert_with_async_without_read_own_writes_in::<
+    SmolSpawner,
+  >());
+}
+
+async fn write_batch_bounded_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let versions = db
+    .write_batch_bounded((0..10).map(|i| (i, i * 10)), 4)
+    .await
+    .unwrap();
+  assert_eq!(versions, vec![1, 2, 3]);
+  assert_eq!(db.version().await, 3);
+
+  let rx = db.read().await;
+  for i in 0..10 {
+    assert_eq!(*rx.get(&i).unwrap().value(), i * 10);
+  }
+
+  let versions = db
+    .write_batch_bounded(core::iter::empty(), 4)
+    .await
+    .unwrap();
+  assert!(versions.is_empty());
+
+  db.close_gracefully(async {}).await.unwrap();
+  assert!(matches!(
+    db.write_batch_bounded((0..10).map(|i| (i, i)), 4).await,
+    Err(WtmError::Transaction(TransactionError::Closed))
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn write_batch_bounded_tokio() {
+  write_batch_bounded_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn write_batch_bounded_async_std() {
+  write_batch_bounded_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn write_batch_bounded_smol() {
+  smol::block_on(write_batch_bounded_in::<SmolSpawner>());
+}
+
+async fn conditional_remove_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(2, 2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert!(!tx.conditional_remove(1, Some(&10)).unwrap());
+  assert!(!tx.conditional_remove(1, None).unwrap());
+  assert!(tx.conditional_remove(1, Some(&1)).unwrap());
+  assert!(tx.get(&1).unwrap().is_none());
+  assert!(!tx.conditional_remove(3, Some(&3)).unwrap());
+  // a missing key matches `None`, but nothing is removed.
+  assert!(!tx.conditional_remove(3, None).unwrap());
+  assert!(!tx.conditional_remove(1, None).unwrap());
+  assert_eq!(tx.pending_removes_count(), 1);
+
+  let mut tx2 = db.write().await;
+  assert!(!tx2.conditional_remove(2, Some(&20)).unwrap());
+
+  tx.insert(2, 20).unwrap();
+  tx.commit().await.unwrap();
+
+  tx2.insert(4, 4).unwrap();
+  assert!(tx2.commit().await.is_err());
+
+  let rx = db.read().await;
+  assert!(rx.get(&1).is_none());
+  assert_eq!(*rx.get(&2).unwrap().value(), 20);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn conditional_remove_tokio() {
+  conditional_remove_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn conditional_remove_async_std() {
+  conditional_remove_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn conditional_remove_smol() {
+  smol::block_on(conditional_remove_in::<SmolSpawner>());
+}
+
+async fn upsert_batch_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.upsert_batch(vec![1, 2, 2], |k, v| v.map_or(*k * 10, |v| v + 1))
+    .unwrap();
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 2);
+  assert_eq!(*rx.get(&2).unwrap().value(), 21);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn upsert_batch_tokio() {
+  upsert_batch_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn upsert_batch_async_std() {
+  upsert_batch_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn upsert_batch_smol() {
+  smol::block_on(upsert_batch_in::<SmolSpawner>());
+}
+
+async fn export_state_in<S: AsyncSpawner>() {
+  use std::{collections::hash_map::DefaultHasher, hash::BuildHasherDefault};
+
+  type Db<S> = OptimisticDb<u64, u64, S, BuildHasherDefault<DefaultHasher>>;
+
+  let leader: Db<S> = OptimisticDb::with_hasher(Default::default()).await;
+  let follower: Db<S> = OptimisticDb::with_hasher(Default::default()).await;
+  for db in [&leader, &follower] {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(3, 3).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = leader.write().await;
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 1);
+  tx.insert(2, 2).unwrap();
+  tx.remove(3).unwrap();
+  let state = tx.export_state().unwrap();
+  assert_eq!(state.read_version, 1);
+  assert_eq!(state.pending_writes, vec![(1, 2, Some(2)), (1, 3, None)]);
+
+  let mut ftx = OptimisticTransaction::from_state(follower.clone(), state.clone())
+    .await
+    .unwrap();
+  assert_eq!(*ftx.get(&2).unwrap().unwrap().value(), 2);
+  ftx.commit().await.unwrap();
+
+  let rx = follower.read().await;
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+  assert!(rx.get(&3).is_none());
+
+  // the follower has moved on, the reads of the state can not be checked anymore.
+  assert!(matches!(
+    OptimisticTransaction::from_state(follower.clone(), state.clone()).await,
+    Err(TransactionError::StaleTransaction { lag: 1 })
+  ));
+
+  // the reads of the state conflict with the writes after its read version.
+  let mut other = follower.write().await;
+  other.insert(1, 10).unwrap();
+  let mut ftx = OptimisticTransaction::from_state(follower.clone(), {
+    let mut state = state;
+    state.read_version = 2;
+    state
+  })
+  .await
+  .unwrap();
+  other.commit().await.unwrap();
+  assert!(ftx.commit().await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn export_state_tokio() {
+  export_state_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn export_state_async_std() {
+  export_state_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn export_state_smol() {
+  smol::block_on(export_state_in::<SmolSpawner>());
+}
+
+async fn scan_prefix_count_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<Vec<u8>, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    for key in [
+      &b"a"[..],
+      b"ab",
+      b"ac",
+      b"b",
+      b"\xff",
+      b"\xff\xff",
+      b"\xff\x01",
+    ] {
+      tx.insert(key.to_vec(), 0).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(b"ad".to_vec(), 0).unwrap();
+  tx.remove(b"ab".to_vec()).unwrap();
+  assert_eq!(tx.scan_prefix_count(b"a").unwrap(), 3);
+  assert_eq!(tx.scan_prefix_count_approx(b"a"), 3);
+  assert_eq!(tx.scan_prefix_count(b"ab").unwrap(), 0);
+  assert_eq!(tx.scan_prefix_count_approx(b"ab"), 1);
+  assert_eq!(tx.scan_prefix_count(b"\xff").unwrap(), 3);
+  assert_eq!(tx.scan_prefix_count(b"\xff\xff").unwrap(), 1);
+  assert_eq!(tx.scan_prefix_count(b"").unwrap(), 7);
+
+  // the prefix count marks the database as read.
+  let mut other = db.write().await;
+  other.insert(b"c".to_vec(), 0).unwrap();
+  other.commit().await.unwrap();
+  assert!(tx.commit().await.is_err());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn scan_prefix_count_tokio() {
+  scan_prefix_count_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn scan_prefix_count_async_std() {
+  scan_prefix_count_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn scan_prefix_count_smol() {
+  smol::block_on(scan_prefix_count_in::<SmolSpawner>());
+}
+
+async fn get_version_or_insert_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  match tx.get_version_or_insert(1, 1, 10).unwrap() {
+    VersionOrInserted::Matched(ent) => assert_eq!(*ent.value(), 1),
+    VersionOrInserted::Inserted(_) => panic!("the version matches"),
+  }
+  match tx.get_version_or_insert(1, 0, 10).unwrap() {
+    VersionOrInserted::Inserted(ent) => assert_eq!(*ent.value(), 10),
+    VersionOrInserted::Matched(_) => panic!("the version does not match"),
+  }
+  match tx.get_version_or_insert(2, 1, 20).unwrap() {
+    VersionOrInserted::Inserted(ent) => assert_eq!(*ent.value(), 20),
+    VersionOrInserted::Matched(_) => panic!("the key does not exist"),
+  }
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 10);
+  assert_eq!(*rx.get(&2).unwrap().value(), 20);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_version_or_insert_tokio() {
+  get_version_or_insert_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_version_or_insert_async_std() {
+  get_version_or_insert_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_version_or_insert_smol() {
+  smol::block_on(get_version_or_insert_in::<SmolSpawner>());
+}
+
+async fn drain_removes_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(2, 2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(3, 3).unwrap();
+  tx.remove(1).unwrap();
+  tx.remove(2).unwrap();
+  assert_eq!(tx.drain_removes().unwrap(), vec![1, 2]);
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 1);
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+  assert_eq!(*rx.get(&3).unwrap().value(), 3);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn drain_removes_tokio() {
+  drain_removes_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn drain_removes_async_std() {
+  drain_removes_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn drain_removes_smol() {
+  smol::block_on(drain_removes_in::<SmolSpawner>());
+}
+
+async fn iter_values_only_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 10).unwrap();
+    tx.insert(2, 20).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(3, 30).unwrap();
+  tx.remove(1).unwrap();
+  assert_eq!(
+    tx.iter_values_only().unwrap().collect::<Vec<_>>(),
+    vec![20, 30]
+  );
+  assert_eq!(tx.iter_keys_only().unwrap().collect::<Vec<_>>(), vec![2, 3]);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn iter_values_only_tokio() {
+  iter_values_only_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn iter_values_only_async_std() {
+  iter_values_only_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn iter_values_only_smol() {
+  smol::block_on(iter_values_only_in::<SmolSpawner>());
+}
+
+async fn peek_commit_ts_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.insert(1, 1).unwrap();
+  let peeked = tx.peek_commit_ts().await.unwrap();
+
+  {
+    let mut other = db.write().await;
+    other.insert(2, 2).unwrap();
+    other.commit().await.unwrap();
+  }
+  assert_eq!(db.read().await.version(), peeked);
+
+  let peeked = tx.peek_commit_ts().await.unwrap();
+  tx.commit().await.unwrap();
+  assert_eq!(db.read().await.version(), peeked);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn peek_commit_ts_tokio() {
+  peek_commit_ts_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn peek_commit_ts_async_std() {
+  peek_commit_ts_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn peek_commit_ts_smol() {
+  smol::block_on(peek_commit_ts_in::<SmolSpawner>());
+}
+
+async fn commit_and_return_entries_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  assert!(tx.commit_and_return_entries().await.unwrap().is_empty());
+
+  let mut tx = db.write().await;
+  tx.insert(1, 1).unwrap();
+  tx.insert(2, 2).unwrap();
+  tx.remove(3).unwrap();
+  let mut entries = tx.commit_and_return_entries().await.unwrap();
+  entries.sort_unstable_by_key(|ent| *ent.key());
+
+  let version = db.read().await.version();
+  assert_eq!(entries.len(), 3);
+  assert!(entries.iter().all(|ent| ent.version() == version));
+  assert_eq!(entries[0].data(), &EntryData::Insert { key: 1, value: 1 });
+  assert_eq!(entries[1].data(), &EntryData::Insert { key: 2, value: 2 });
+  assert_eq!(entries[2].data(), &EntryData::Remove(3));
+  assert_eq!(*db.read().await.get(&2).unwrap().value(), 2);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn commit_and_return_entries_tokio() {
+  commit_and_return_entries_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn commit_and_return_entries_async_std() {
+  commit_and_return_entries_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn commit_and_return_entries_smol() {
+  smol::block_on(commit_and_return_entries_in::<SmolSpawner>());
+}
+
+async fn pessimistic_lock_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let guard = db.lock_key(&1).await;
+    assert_eq!(guard.key(), &1);
+    assert!(db.lock_key(&1).now_or_never().is_none());
+    assert!(db.lock_key(&2).now_or_never().is_some());
+  }
+  assert!(db.lock_key(&1).now_or_never().is_some());
+
+  let mut tx = db.write_pessimistic().await.unwrap();
+  assert!(tx.get(&1).await.unwrap().is_none());
+  // locking a key already locked by the transaction does not wait.
+  tx.lock_key(&1).await;
+  tx.insert(1, 1).unwrap();
+
+  let mut other = db.write_pessimistic().await.unwrap();
+  assert!(other.get(&1).now_or_never().is_none());
+  assert!(other.get(&2).await.unwrap().is_none());
+
+  tx.commit().await.unwrap();
+  assert!(db.lock_key(&1).now_or_never().is_some());
+  assert!(db.lock_key(&2).now_or_never().is_none());
+  drop(other);
+  assert!(db.lock_key(&2).now_or_never().is_some());
+  assert_eq!(*db.read().await.get(&1).unwrap().value(), 1);
+
+  // the first read is taken after the key is locked, so the commit of the previous holder
+  // of the lock is seen instead of making the transaction conflict.
+  let mut tx = db.write_pessimistic().await.unwrap();
+  tx.get(&1).await.unwrap();
+  tx.insert(1, 2).unwrap();
+  let mut other = db.write_pessimistic().await.unwrap();
+  tx.commit().await.unwrap();
+  assert_eq!(*other.get(&1).await.unwrap().unwrap().value(), 2);
+  other.insert(1, 3).unwrap();
+  other.commit().await.unwrap();
+  assert_eq!(*db.read().await.get(&1).unwrap().value(), 3);
+
+  db.close_gracefully(async {}).await.unwrap();
+  assert!(matches!(
+    db.write_pessimistic().await,
+    Err(TransactionError::Closed)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn pessimistic_lock_tokio() {
+  pessimistic_lock_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn pessimistic_lock_async_std() {
+  pessimistic_lock_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn pessimistic_lock_smol() {
+  smol::block_on(pessimistic_lock_in::<SmolSpawner>());
+}
+
+async fn try_commit_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  assert!(tx.get(&1).unwrap().is_none());
+  tx.insert(2, 2).unwrap();
+
+  let mut other = db.write().await;
+  other.insert(1, 10).unwrap();
+  other.commit().await.unwrap();
+
+  // a blind retry keeps conflicting, instead of overwriting the conflicting commit.
+  let version = tx.version();
+  for _ in 0..2 {
+    assert!(matches!(
+      tx.try_commit().await.unwrap_err(),
+      WtmError::Transaction(TransactionError::Conflict)
+    ));
+    assert_eq!(tx.version(), version);
+  }
+
+  // the key is read again at the new read version, then the transaction is retried.
+  tx.reset_read_ts_to_latest().await.unwrap();
+  assert!(tx.version() > version);
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 10);
+  tx.insert(3, 3).unwrap();
+  tx.try_commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 10);
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+  assert_eq!(*rx.get(&3).unwrap().value(), 3);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn try_commit_tokio() {
+  try_commit_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn try_commit_async_std() {
+  try_commit_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn try_commit_smol() {
+  smol::block_on(try_commit_in::<SmolSpawner>());
+}
+
+async fn set_read_ts_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  let version = tx.version();
+  assert!(tx.get(&1).unwrap().is_none());
+
+  for i in 1..=2 {
+    let mut other = db.write().await;
+    other.insert(1, i).unwrap();
+    other.commit().await.unwrap();
+  }
+
+  assert!(matches!(
+    tx.set_read_ts(version + 3).await,
+    Err(TransactionError::InvalidVersion)
+  ));
+  tx.set_read_ts(version + 1).await.unwrap();
+  assert_eq!(tx.version(), version + 1);
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 1);
+  assert!(matches!(
+    tx.set_read_ts(version).await,
+    Err(TransactionError::InvalidVersion)
+  ));
+
+  tx.set_read_ts(version + 2).await.unwrap();
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 2);
+  assert_eq!(db.min_active_read_version(), Some(version + 2));
+  tx.insert(2, 2).unwrap();
+  tx.commit().await.unwrap();
+  assert_eq!(db.min_active_read_version(), None);
+
+  // the stale read of the old snapshot is dropped, so the commit in between does not
+  // make the transaction conflict.
+  let mut tx = db.write().await;
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 2);
+  let mut other = db.write().await;
+  other.insert(1, 3).unwrap();
+  other.commit().await.unwrap();
+  tx.set_read_ts(db.version().await).await.unwrap();
+  tx.insert(3, 3).unwrap();
+  tx.commit().await.unwrap();
+
+  // the reads of the new snapshot are still checked.
+  let mut tx = db.write().await;
+  let mut other = db.write().await;
+  other.insert(1, 4).unwrap();
+  other.commit().await.unwrap();
+  tx.set_read_ts(db.version().await).await.unwrap();
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 4);
+  tx.insert(1, 5).unwrap();
+  let mut other = db.write().await;
+  other.insert(1, 6).unwrap();
+  other.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn set_read_ts_tokio() {
+  set_read_ts_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn set_read_ts_async_std() {
+  set_read_ts_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn set_read_ts_smol() {
+  smol::block_on(set_read_ts_in::<SmolSpawner>());
+}
+
+async fn get_multiple_and_aggregate_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(2, 2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(3, 3).unwrap();
+  tx.remove(2).unwrap();
+  let (sum, missing) = tx
+    .get_multiple_and_aggregate(&[1, 2, 3, 4], |(sum, missing), ent| match ent {
+      Some(ent) => (sum + *ent.value(), missing),
+      None => (sum, missing + 1),
+    })
+    .unwrap();
+  assert_eq!((sum, missing), (4, 2));
+
+  // the missing keys are marked as read too.
+  let mut other = db.write().await;
+  other.insert(4, 4).unwrap();
+  other.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_multiple_and_aggregate_tokio() {
+  get_multiple_and_aggregate_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_multiple_and_aggregate_async_std() {
+  get_multiple_and_aggregate_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_multiple_and_aggregate_smol() {
+  smol::block_on(get_multiple_and_aggregate_in::<SmolSpawner>());
+}
+
+async fn compact_below_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut old = None;
+  for i in 1..=3 {
+    let mut tx = db.write().await;
+    tx.insert(1, i).unwrap();
+    match i {
+      1 => tx.insert(2, 2).unwrap(),
+      2 => tx.remove(2).unwrap(),
+      _ => {}
+    }
+    tx.commit().await.unwrap();
+    old.get_or_insert(db.read().await);
+  }
+
+  // the versions may still be read by the read transaction at version 1.
+  assert!(matches!(
+    db.compact_below_version(2).await,
+    Err(TransactionError::InvalidVersion)
+  ));
+  drop(old);
+
+  // key 1 at version 1 and 2, and both versions of the removed key 2.
+  let stats = db.compact_below_version(3).await.unwrap();
+  assert_eq!(stats.versions_removed, 4);
+  assert!(stats.bytes_reclaimed > 0);
+  assert_eq!(
+    db.compact_below_version(3).await.unwrap(),
+    CompactionStats::default()
+  );
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 3);
+  assert!(rx.get(&2).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn compact_below_version_tokio() {
+  compact_below_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn compact_below_version_async_std() {
+  compact_below_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn compact_below_version_smol() {
+  smol::block_on(compact_below_version_in::<SmolSpawner>());
+}
+
+async fn read_own_writes_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(1, 2).unwrap();
+  tx.insert(2, 2).unwrap();
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 2);
+  assert!(tx.contains_key(&2).unwrap());
+
+  tx.set_read_own_writes(false);
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 1);
+  assert!(tx.get(&2).unwrap().is_none());
+  assert!(!tx.contains_key(&2).unwrap());
+  tx.commit().await.unwrap();
+
+  let rx = db.read().await;
+  assert_eq!(*rx.get(&1).unwrap().value(), 2);
+  assert_eq!(*rx.get(&2).unwrap().value(), 2);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn read_own_writes_tokio() {
+  read_own_writes_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn read_own_writes_async_std() {
+  read_own_writes_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn read_own_writes_smol() {
+  smol::block_on(read_own_writes_in::<SmolSpawner>());
+}
+
+async fn try_write_after_close_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  assert!(db.try_write().await.is_ok());
+
+  db.close_gracefully(async {}).await.unwrap();
+  assert!(matches!(
+    db.try_write().await,
+    Err(TransactionError::Closed)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn try_write_after_close_tokio() {
+  try_write_after_close_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn try_write_after_close_async_std() {
+  try_write_after_close_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn try_write_after_close_smol() {
+  smol::block_on(try_write_after_close_in::<SmolSpawner>());
+}
+
+async fn count_conflict_keys_in_range_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  for i in 0..10 {
+    tx.insert(i, i).unwrap();
+  }
+  tx.remove(20).unwrap();
+  assert_eq!(tx.count_conflict_keys_in_range(..), Some(11));
+  assert_eq!(tx.count_conflict_keys_in_range(3..6), Some(3));
+  assert_eq!(tx.count_conflict_keys_in_range(8..), Some(3));
+  assert_eq!(tx.count_conflict_keys_in_range(11..20), Some(0));
+
+  tx.commit().await.unwrap();
+  assert_eq!(tx.count_conflict_keys_in_range(..), None);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn count_conflict_keys_in_range_tokio() {
+  count_conflict_keys_in_range_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn count_conflict_keys_in_range_async_std() {
+  count_conflict_keys_in_range_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn count_conflict_keys_in_range_smol() {
+  smol::block_on(count_conflict_keys_in_range_in::<SmolSpawner>());
+}
+
+async fn get_or_compute_in<S: AsyncSpawner>() {
+  #[derive(Debug)]
+  enum Error {
+    Compute,
+    Transaction(TransactionError<Infallible, Infallible>),
+  }
+
+  impl From<TransactionError<Infallible, Infallible>> for Error {
+    fn from(e: TransactionError<Infallible, Infallible>) -> Self {
+      Self::Transaction(e)
+    }
+  }
+
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert_eq!(
+    *tx
+      .get_or_compute(1, |_| async { Err::<u64, _>(Error::Compute) })
+      .await
+      .unwrap()
+      .value(),
+    1
+  );
+  assert_eq!(
+    *tx
+      .get_or_compute(2, |k| {
+        let v = *k * 10;
+        async move { Ok::<_, Error>(v) }
+      })
+      .await
+      .unwrap()
+      .value(),
+    20
+  );
+
+  assert!(matches!(
+    tx.get_or_compute(3, |_| async { Err::<u64, _>(Error::Compute) })
+      .await,
+    Err(Error::Compute)
+  ));
+  assert!(tx.get(&3).unwrap().is_none());
+
+  tx.rollback().unwrap();
+  tx.commit().await.unwrap();
+  assert!(matches!(
+    tx.get_or_compute(4, |_| async { Ok::<_, Error>(4) }).await,
+    Err(Error::Transaction(TransactionError::Discard))
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn get_or_compute_tokio() {
+  get_or_compute_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn get_or_compute_async_std() {
+  get_or_compute_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn get_or_compute_smol() {
+  smol::block_on(get_or_compute_in::<SmolSpawner>());
+}
+
+async fn iter_from_to_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    for i in 1..=3 {
+      tx.insert(i, i).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(4, 4).unwrap();
+  tx.remove(2).unwrap();
+
+  let mut check = |bounds: (Bound<u64>, Bound<u64>), expected: Vec<u64>| {
+    let keys = tx
+      .iter_from_to(bounds)
+      .unwrap()
+      .map(|ent| *ent.key())
+      .collect::<Vec<_>>();
+    assert_eq!(keys, expected);
+  };
+  check((Bound::Included(1), Bound::Included(4)), vec![1, 3, 4]);
+  check((Bound::Included(1), Bound::Excluded(4)), vec![1, 3]);
+  check((Bound::Excluded(1), Bound::Included(4)), vec![3, 4]);
+  check((Bound::Excluded(1), Bound::Excluded(4)), vec![3]);
+  check((Bound::Unbounded, Bound::Excluded(3)), vec![1]);
+  check((Bound::Excluded(3), Bound::Unbounded), vec![4]);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn iter_from_to_tokio() {
+  iter_from_to_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn iter_from_to_async_std() {
+  iter_from_to_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn iter_from_to_smol() {
+  smol::block_on(iter_from_to_in::<SmolSpawner>());
+}
+
+async fn merge_pending_into_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut src = db.write().await;
+  let mut target = db.write().await;
+  assert!(src.get(&9).unwrap().is_none());
+  src.insert(1, 1).unwrap();
+  src.remove(2).unwrap();
+  target.insert(3, 3).unwrap();
+
+  src.merge_pending_into(&mut target).unwrap();
+  assert_eq!(src.total_entry_count(), 0);
+  assert!(src.get(&1).unwrap().is_none());
+  assert_eq!(target.total_entry_count(), 3);
+  assert_eq!(*target.get(&1).unwrap().unwrap().value(), 1);
+
+  // the transactions of another database can not be merged.
+  let other: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+  let mut foreign = other.write().await;
+  assert_eq!(
+    target.merge_pending_into(&mut foreign).unwrap_err(),
+    TransactionError::Illegal
+  );
+
+  // the read of the source is merged, so a concurrent write conflicts with the target.
+  let mut tx = db.write().await;
+  tx.insert(9, 9).unwrap();
+  tx.commit().await.unwrap();
+  assert!(matches!(
+    target.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn merge_pending_into_tokio() {
+  merge_pending_into_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn merge_pending_into_async_std() {
+  merge_pending_into_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn merge_pending_into_smol() {
+  smol::block_on(merge_pending_into_in::<SmolSpawner>());
+}
+
+async fn merge_pending_into_earlier_read_version_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut target = db.write().await;
+  {
+    let mut tx = db.write().await;
+    tx.insert(5, 5).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut src = db.write().await;
+  assert!(src.version() > target.version());
+  src.insert(1, 1).unwrap();
+  src.merge_pending_into(&mut target).unwrap();
+  let read_ts = target.version();
+  assert_eq!(target.get(&1).unwrap().unwrap().version(), read_ts);
+
+  // the target can not read at a later version than the source.
+  let mut src = db.write().await;
+  src.insert(2, 2).unwrap();
+  assert!(matches!(
+    target.merge_pending_into(&mut src).unwrap_err(),
+    TransactionError::StaleTransaction { lag: 1 }
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn merge_pending_into_earlier_read_version_tokio() {
+  merge_pending_into_earlier_read_version_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn merge_pending_into_earlier_read_version_async_std() {
+  merge_pending_into_earlier_read_version_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn merge_pending_into_earlier_read_version_smol() {
+  smol::block_on(merge_pending_into_earlier_read_version_in::<SmolSpawner>());
+}
+
+async fn iter_exclude_pending_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(1, 1).unwrap();
+    tx.insert(2, 2).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(1, 10).unwrap();
+  tx.insert(3, 3).unwrap();
+  tx.remove(2).unwrap();
+
+  let collect = |iter: TransactionIter<'_, u64, u64, _>| {
+    iter
+      .map(|ent| (*ent.key(), *ent.value()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    collect(tx.iter_with_options(IteratorOptions::new()).unwrap()),
+    vec![(1, 10), (3, 3)]
+  );
+  assert_eq!(
+    collect(
+      tx.iter_with_options(IteratorOptions::new().exclude_pending(true))
+        .unwrap()
+    ),
+    vec![(1, 1), (2, 2)]
+  );
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn iter_exclude_pending_tokio() {
+  iter_exclude_pending_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn iter_exclude_pending_async_std() {
+  iter_exclude_pending_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn iter_exclude_pending_smol() {
+  smol::block_on(iter_exclude_pending_in::<SmolSpawner>());
+}
+
+async fn reset_read_ts_to_latest_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  assert!(tx.get(&1).unwrap().is_none());
+  tx.insert(2, 2).unwrap();
+
+  {
+    let mut other = db.write().await;
+    other.insert(1, 1).unwrap();
+    other.commit().await.unwrap();
+  }
+
+  // the stale read of the key is cleared, the new snapshot sees the committed value.
+  tx.reset_read_ts_to_latest().await.unwrap();
+  assert_eq!(tx.version(), db.version().await);
+  assert_eq!(*tx.get(&2).unwrap().unwrap().value(), 2);
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 1);
+  tx.commit().await.unwrap();
+
+  let rtx = db.read().await;
+  assert_eq!(*rtx.get(&2).unwrap().value(), 2);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn reset_read_ts_to_latest_tokio() {
+  reset_read_ts_to_latest_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn reset_read_ts_to_latest_async_std() {
+  reset_read_ts_to_latest_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn reset_read_ts_to_latest_smol() {
+  smol::block_on(reset_read_ts_to_latest_in::<SmolSpawner>());
+}
+
+async fn insert_many_raw_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    tx.insert(3, 3).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(1, 1).unwrap();
+  tx.insert(2, 2).unwrap();
+  tx.remove(3).unwrap();
+  let entries = tx.into_entries().unwrap();
+
+  let mut tx = db.write().await;
+  tx.insert_many_raw(entries).unwrap();
+  assert_eq!(tx.total_entry_count(), 3);
+  tx.commit().await.unwrap();
+
+  let rtx = db.read().await;
+  assert_eq!(*rtx.get(&1).unwrap().value(), 1);
+  assert_eq!(*rtx.get(&2).unwrap().value(), 2);
+  assert!(rtx.get(&3).is_none());
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn insert_many_raw_tokio() {
+  insert_many_raw_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn insert_many_raw_async_std() {
+  insert_many_raw_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn insert_many_raw_smol() {
+  smol::block_on(insert_many_raw_in::<SmolSpawner>());
+}
+
+#[cfg(feature = "parallel-reads")]
+async fn parallel_get_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    for i in 0..8 {
+      tx.insert(i, i).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(1, 10).unwrap();
+  tx.remove(2).unwrap();
+  let keys = [0, 1, 2, 3, 4, 5, 6, 7, 8];
+  let values = tx.parallel_get(&keys, 3).await.unwrap();
+  assert_eq!(
+    values,
+    vec![
+      Some(0),
+      Some(10),
+      None,
+      Some(3),
+      Some(4),
+      Some(5),
+      Some(6),
+      Some(7),
+      None
+    ]
+  );
+  // the values do not depend on the number of the tasks.
+  assert_eq!(tx.parallel_get(&keys, 0).await.unwrap(), values);
+  assert_eq!(tx.parallel_get(&keys, 100).await.unwrap(), values);
+  assert!(tx.parallel_get(&[], 3).await.unwrap().is_empty());
+
+  // all the keys are marked as read.
+  let mut other = db.write().await;
+  other.insert(8, 8).unwrap();
+  other.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(all(feature = "parallel-reads", feature = "tokio"))]
+async fn parallel_get_tokio() {
+  parallel_get_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(all(feature = "parallel-reads", feature = "async-std"))]
+async fn parallel_get_async_std() {
+  parallel_get_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(all(feature = "parallel-reads", feature = "smol"))]
+fn parallel_get_smol() {
+  smol::block_on(parallel_get_in::<SmolSpawner>());
+}
+
+async fn with_metadata_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await.with_metadata("trace-1".to_string());
+  assert_eq!(tx.metadata(), "trace-1");
+  tx.metadata_mut().push_str("-a");
+  tx.insert(1, 1).unwrap();
+  assert_eq!(*tx.get(&1).unwrap().unwrap().value(), 1);
+
+  let (sender, receiver) = futures::channel::oneshot::channel();
+  let handle = tx
+    .commit_with_task::<_, std::convert::Infallible, ()>(move |rst, metadata| async move {
+      rst.unwrap();
+      sender.send(metadata).unwrap();
+    })
+    .await
+    .unwrap();
+  let _ = handle.await;
+  assert_eq!(receiver.await.unwrap(), "trace-1-a");
+
+  let rtx = db.read().await;
+  assert_eq!(*rtx.get(&1).unwrap().value(), 1);
+
+  let (tx, metadata) = db.write().await.with_metadata(7u32).into_parts();
+  assert_eq!(metadata, 7);
+  assert_eq!(tx.version(), 1);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn with_metadata_tokio() {
+  with_metadata_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn with_metadata_async_std() {
+  with_metadata_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn with_metadata_smol() {
+  smol::block_on(with_metadata_in::<SmolSpawner>());
+}
+
+async fn into_stream_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  for i in 0..3 {
+    tx.insert(i, i).unwrap();
+  }
+  let events = tx.into_stream().await.collect::<Vec<_>>().await;
+  assert_eq!(
+    events,
+    vec![
+      CommitEvent::EntryApplied(0),
+      CommitEvent::EntryApplied(1),
+      CommitEvent::EntryApplied(2),
+      CommitEvent::Committed(1),
+    ]
+  );
+  assert_eq!(*db.read().await.get(&2).unwrap().value(), 2);
+
+  let mut tx = db.write().await;
+  assert!(tx.get(&0).unwrap().is_some());
+  tx.insert(0, 10).unwrap();
+  {
+    let mut other = db.write().await;
+    other.insert(0, 20).unwrap();
+    other.commit().await.unwrap();
+  }
+  let events = tx.into_stream().await.collect::<Vec<_>>().await;
+  assert_eq!(events, vec![CommitEvent::Conflict]);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn into_stream_tokio() {
+  into_stream_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn into_stream_async_std() {
+  into_stream_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn into_stream_smol() {
+  smol::block_on(into_stream_in::<SmolSpawner>());
+}
+
+async fn iter_version_range_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  // version 1 writes 1 and 2, version 2 writes 3, version 3 overwrites 1 and removes 2.
+  for (inserts, removes) in [(vec![1, 2], vec![]), (vec![3], vec![]), (vec![1], vec![2])] {
+    let mut tx = db.write().await;
+    for k in inserts {
+      tx.insert(k, k * 10).unwrap();
+    }
+    for k in removes {
+      tx.remove(k).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  tx.insert(4, 40).unwrap();
+  let mut check = |from: u64, to: u64, expected: Vec<(u64, u64)>| {
+    let entries = tx
+      .iter_with_options(IteratorOptions::new().with_version_range(from, to))
+      .unwrap()
+      .map(|ent| (*ent.key(), ent.version()))
+      .collect::<Vec<_>>();
+    assert_eq!(entries, expected);
+  };
+  check(1, 1, vec![(1, 1), (2, 1)]);
+  check(2, 2, vec![(3, 2)]);
+  check(2, 3, vec![(1, 3), (3, 2)]);
+  check(1, 3, vec![(1, 3), (3, 2)]);
+
+  assert_eq!(
+    tx.iter_with_options(IteratorOptions::new().with_version_range(3, 2))
+      .err(),
+    Some(TransactionError::InvalidVersion)
+  );
+  assert_eq!(
+    tx.iter_with_options(IteratorOptions::new().with_version_range(1, 4))
+      .err(),
+    Some(TransactionError::InvalidVersion)
+  );
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn iter_version_range_tokio() {
+  iter_version_range_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn iter_version_range_async_std() {
+  iter_version_range_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn iter_version_range_smol() {
+  smol::block_on(iter_version_range_in::<SmolSpawner>());
+}
+
+async fn evict_conflict_keys_older_than_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  for k in 1..=3 {
+    assert!(tx.get(&k).unwrap().is_none());
+  }
+  tx.insert(10, 10).unwrap();
+  assert_eq!(tx.evict_conflict_keys_older_than(1), 2);
+
+  // the forgotten reads do not conflict, the last read still does.
+  let mut other = db.write().await;
+  other.insert(1, 1).unwrap();
+  other.commit().await.unwrap();
+  let mut probe = tx.fork().await.unwrap();
+  probe.commit().await.unwrap();
+
+  let mut other = db.write().await;
+  other.insert(3, 3).unwrap();
+  other.commit().await.unwrap();
+  assert!(matches!(
+    tx.commit().await.unwrap_err(),
+    WtmError::Transaction(TransactionError::Conflict)
+  ));
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn evict_conflict_keys_older_than_tokio() {
+  evict_conflict_keys_older_than_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn evict_conflict_keys_older_than_async_std() {
+  evict_conflict_keys_older_than_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn evict_conflict_keys_older_than_smol() {
+  smol::block_on(evict_conflict_keys_older_than_in::<SmolSpawner>());
+}
+
+async fn retry_budget_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  let mut tx = db.write().await;
+  tx.set_retry_budget(1);
+  assert!(tx.get(&1).unwrap().is_none());
+  tx.insert(2, 2).unwrap();
+
+  for round in 0..2 {
+    let mut other = db.write().await;
+    other.insert(1, round).unwrap();
+    other.commit().await.unwrap();
+
+    let err = tx.try_commit().await.unwrap_err();
+    if round == 0 {
+      assert!(matches!(
+        err,
+        WtmError::Transaction(TransactionError::Conflict)
+      ));
+      assert_eq!(tx.retry_count(), 1);
+    } else {
+      assert!(matches!(
+        err,
+        WtmError::Transaction(TransactionError::RetriesExhausted(1))
+      ));
+    }
+  }
+  assert_eq!(tx.retry_count(), 1);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn retry_budget_tokio() {
+  retry_budget_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn retry_budget_async_std() {
+  retry_budget_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn retry_budget_smol() {
+  smol::block_on(retry_budget_in::<SmolSpawner>());
+}
+
+async fn count_all_in<S: AsyncSpawner>() {
+  let db: OptimisticDb<u64, u64, S> = OptimisticDb::new().await;
+
+  {
+    let mut tx = db.write().await;
+    for i in 0..5 {
+      tx.insert(i, i).unwrap();
+    }
+    tx.commit().await.unwrap();
+  }
+  {
+    let mut tx = db.write().await;
+    tx.remove(4).unwrap();
+    tx.commit().await.unwrap();
+  }
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.count_all().unwrap(), 4);
+  // an overwrite and a remove of a missing key do not change the count.
+  tx.insert(0, 10).unwrap();
+  tx.remove(9).unwrap();
+  assert_eq!(tx.count_all().unwrap(), 4);
+  tx.insert(4, 4).unwrap();
+  tx.insert(5, 5).unwrap();
+  tx.remove(1).unwrap();
+  tx.remove(2).unwrap();
+  assert_eq!(tx.count_all().unwrap(), 4);
+  tx.remove(3).unwrap();
+  assert_eq!(tx.count_all().unwrap(), 3);
+  tx.commit().await.unwrap();
+
+  let mut tx = db.write().await;
+  assert_eq!(tx.count_all().unwrap(), 3);
+}
+
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn count_all_tokio() {
+  count_all_in::<TokioSpawner>().await;
+}
+
+#[async_std::test]
+#[cfg(feature = "async-std")]
+async fn count_all_async_std() {
+  count_all_in::<AsyncStdSpawner>().await;
+}
+
+#[test]
+#[cfg(feature = "smol")]
+fn count_all_smol() {
+  smol::block_on(count_all_in::<SmolSpawner>());
+}
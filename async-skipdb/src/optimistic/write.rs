@@ -1,6 +1,10 @@
-use std::{convert::Infallible, future::Future};
+use std::{convert::Infallible, future::Future, ops::Bound};
 
-use async_txn::{error::WtmError, PwmComparableRange};
+use async_txn::{
+  error::WtmError, Entry, EntryValue, OneOrMore, Pwm, PwmComparableRange, PwmRange,
+  TransactionState,
+};
+use futures::{SinkExt, Stream};
 use skipdb_core::rev_range::WriteTransactionRevRange;
 
 use super::*;
@@ -18,7 +22,10 @@ where
   SP: AsyncSpawner,
 {
   #[inline]
-  pub(super) async fn new(db: OptimisticDb<K, V, SP, S>, cap: Option<usize>) -> Self {
+  pub(super) async fn new(
+    db: OptimisticDb<K, V, SP, S>,
+    cap: Option<usize>,
+  ) -> Result<Self, TransactionError<Infallible, Infallible>> {
     let wtm = db
       .inner
       .tm
@@ -26,9 +33,158 @@ where
         (),
         HashCmOptions::with_capacity(db.inner.hasher.clone(), cap.unwrap_or(8)),
       )
-      .await
-      .unwrap();
-    Self { db, wtm }
+      .await?;
+    Ok(Self { db, wtm })
+  }
+
+  /// Forks this transaction, the new transaction has the same read version, and a copy of
+  /// the pending writes and the tracked reads and writes of this transaction.
+  #[inline]
+  pub async fn fork(&self) -> Result<Self, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    V: Clone,
+  {
+    Ok(Self {
+      db: self.db.clone(),
+      wtm: self.wtm.fork().await?,
+    })
+  }
+
+  /// Creates a new transaction with a fresh read version from the pending writes of a
+  /// discarded (but not committed) transaction.
+  ///
+  /// Returns [`TransactionError::StaleTransaction`] if the read version of the discarded
+  /// transaction lags more than `max_staleness` versions behind the latest version.
+  #[inline]
+  pub async fn absorb_discard(
+    discarded: Self,
+    max_staleness: u64,
+  ) -> Result<Self, TransactionError<Infallible, Infallible>> {
+    let Self { db, wtm } = discarded;
+    let wtm = db
+      .inner
+      .tm
+      .absorb_discard(
+        wtm,
+        max_staleness,
+        HashCmOptions::new(db.inner.hasher.clone()),
+      )
+      .await?;
+    Ok(Self { db, wtm })
+  }
+
+  /// Exports the state of this transaction, which can be shipped to another database,
+  /// e.g. a follower of a replicated database, to reconstruct the transaction by
+  /// [`from_state`](OptimisticTransaction::from_state).
+  #[inline]
+  pub fn export_state(
+    &self,
+  ) -> Result<TransactionState<K, V>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    V: Clone,
+  {
+    self.wtm.export_state_blocking()
+  }
+
+  /// Reconstructs a transaction from the state exported by
+  /// [`export_state`](OptimisticTransaction::export_state).
+  ///
+  /// Both databases must use the same hasher. The current version of `db` must be the read
+  /// version of the state, see [`AsyncWtm::import_state_blocking`] for the errors.
+  pub async fn from_state(
+    db: OptimisticDb<K, V, SP, S>,
+    state: TransactionState<K, V>,
+  ) -> Result<Self, TransactionError<Infallible, Infallible>> {
+    let mut txn = Self::new(db, Some(state.reads.len() + state.conflict_keys.len())).await?;
+    txn.wtm.import_state_blocking(state)?;
+    Ok(txn)
+  }
+}
+
+/// Options for [`OptimisticTransaction::iter_with_options`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IteratorOptions {
+  /// Whether to skip the pending writes of the transaction and iterate over the committed
+  /// entries only. Default is `false`.
+  pub exclude_pending: bool,
+  /// The lowest version of the version window, see
+  /// [`with_version_range`](IteratorOptions::with_version_range). Default is `None`.
+  pub version_from: Option<u64>,
+  /// The highest version of the version window, see
+  /// [`with_version_range`](IteratorOptions::with_version_range). Default is `None`.
+  pub version_to: Option<u64>,
+}
+
+impl IteratorOptions {
+  /// Creates a new `IteratorOptions` with the default options.
+  #[inline]
+  pub const fn new() -> Self {
+    Self {
+      exclude_pending: false,
+      version_from: None,
+      version_to: None,
+    }
+  }
+
+  /// Sets whether to skip the pending writes of the transaction.
+  #[inline]
+  pub const fn exclude_pending(mut self, v: bool) -> Self {
+    self.exclude_pending = v;
+    self
+  }
+
+  /// Iterates over the keys modified in `[from, to]` only, each yielded with its latest
+  /// version in the window. The keys removed at `to` are not yielded, and the pending writes
+  /// are always skipped, as they are not committed at any version yet.
+  #[inline]
+  pub const fn with_version_range(mut self, from: u64, to: u64) -> Self {
+    self.version_from = Some(from);
+    self.version_to = Some(to);
+    self
+  }
+
+  #[inline]
+  const fn is_version_filtered(&self) -> bool {
+    self.version_from.is_some() || self.version_to.is_some()
+  }
+}
+
+/// The events of the commit driven by [`OptimisticTransaction::into_stream`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommitEvent<K> {
+  /// The write of the key has been applied to the database.
+  EntryApplied(K),
+  /// The transaction has been committed at the commit version.
+  Committed(u64),
+  /// The transaction conflicts with another transaction, nothing is applied.
+  Conflict,
+  /// The commit failed with the error, nothing is applied.
+  Failed(TransactionError<Infallible, Infallible>),
+}
+
+/// The outcome of [`OptimisticTransaction::get_version_or_insert`].
+pub enum VersionOrInserted<'a, K, V> {
+  /// The current version of the key matches, holds the current value.
+  Matched(Ref<'a, K, V>),
+  /// The version does not match or the key does not exist, holds the inserted value.
+  Inserted(Ref<'a, K, V>),
+}
+
+/// Iterates over the pending writes of the transaction.
+impl<'a, K, V, SP, S> IntoIterator for &'a OptimisticTransaction<K, V, SP, S>
+where
+  K: Ord,
+  SP: AsyncSpawner,
+{
+  type Item = (&'a K, &'a EntryValue<V>);
+
+  type IntoIter = <&'a AsyncWtm<K, V, HashCm<K, S>, BTreePwm<K, V>, SP> as IntoIterator>::IntoIter;
+
+  #[inline]
+  fn into_iter(self) -> Self::IntoIter {
+    (&self.wtm).into_iter()
   }
 }
 
@@ -39,6 +195,16 @@ where
   S: BuildHasher + Send + Sync + 'static,
   SP: AsyncSpawner,
 {
+  /// Returns the version the transaction would get if it was committed now, without
+  /// committing it.
+  ///
+  /// The returned version is advisory only, the actual commit version may be different
+  /// because other transactions may commit in between.
+  #[inline]
+  pub async fn peek_commit_ts(&self) -> Result<u64, TransactionError<Infallible, Infallible>> {
+    self.wtm.peek_commit_ts().await
+  }
+
   /// Commits the transaction, following these steps:
   ///
   /// 1. If there are no writes, return immediately.
@@ -50,10 +216,10 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. If callback is provided, Badger will return immediately after checking
-  /// for conflicts. Writes to the database will happen in the background.  If
-  /// there is a conflict, an error will be returned and the callback will not
-  /// run. If there are no conflicts, the callback will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    for conflicts. Writes to the database will happen in the background.  If
+  ///    there is a conflict, an error will be returned and the callback will not
+  ///    run. If there are no conflicts, the callback will be called in the
+  ///    background upon successful completion of writes or any error during write.
   #[inline]
   pub async fn commit(
     &mut self,
@@ -67,6 +233,130 @@ where
       })
       .await
   }
+
+  /// Acts like [`commit`](OptimisticTransaction::commit), but the transaction is not discarded
+  /// if it conflicts, so it can be retried with the same pending writes.
+  ///
+  /// On [`TransactionError::Conflict`], the transaction keeps conflicting until it is re-pinned
+  /// by [`reset_read_ts_to_latest`](OptimisticTransaction::reset_read_ts_to_latest), the caller
+  /// should read the keys again and update the pending writes before retrying.
+  #[inline]
+  pub async fn try_commit(&mut self) -> Result<(), WtmError<Infallible, Infallible, Infallible>> {
+    let db = self.db.clone();
+    self
+      .wtm
+      .try_commit(|ents| async move {
+        db.inner.map.apply(ents);
+        Ok(())
+      })
+      .await
+  }
+
+  /// Advances the read version of the transaction to `new_ts`, e.g. to see the newer
+  /// committed data before retrying a [`try_commit`](OptimisticTransaction::try_commit).
+  /// The reads tracked so far are cleared, the keys must be read again to be tracked.
+  ///
+  /// See [`AsyncWtm::set_read_ts`] for the errors.
+  #[inline]
+  pub async fn set_read_ts(
+    &mut self,
+    new_ts: u64,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.set_read_ts(new_ts).await
+  }
+
+  /// Re-pins the transaction at the latest committed version without discarding the pending
+  /// writes, the reads tracked so far are cleared.
+  #[inline]
+  pub async fn reset_read_ts_to_latest(
+    &mut self,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.reset_read_ts_to_latest().await
+  }
+
+  /// Forgets all but the last `n_reads_ago` reads of the transaction, returns the number of the
+  /// forgotten reads.
+  ///
+  /// # Warning
+  /// The concurrent writes to the forgotten keys no longer make this transaction conflict, see
+  /// [`AsyncWtm::evict_conflict_keys_older_than`].
+  #[inline]
+  pub fn evict_conflict_keys_older_than(&mut self, n_reads_ago: usize) -> usize {
+    self.wtm.evict_conflict_keys_older_than(n_reads_ago)
+  }
+
+  /// Acts like [`commit`](OptimisticTransaction::commit), but the transaction is committed at
+  /// the given version instead of the next one, e.g. a version from an external clock.
+  ///
+  /// Returns [`TransactionError::InvalidVersion`] if the version is not greater than the read
+  /// version of the transaction or the latest committed version.
+  #[inline]
+  pub async fn commit_at_version(
+    &mut self,
+    version: u64,
+  ) -> Result<(), WtmError<Infallible, Infallible, Infallible>> {
+    let db = self.db.clone();
+    self
+      .wtm
+      .commit_at_version(version, |ents| async move {
+        db.inner.map.apply(ents);
+        Ok(())
+      })
+      .await
+  }
+
+  /// Acts like [`commit`](OptimisticTransaction::commit), but returns the committed entries
+  /// with their commit version, e.g. for audit logging.
+  ///
+  /// The entries are cloned before they are applied to the database.
+  #[inline]
+  pub async fn commit_and_return_entries(
+    &mut self,
+  ) -> Result<Vec<Entry<K, V>>, WtmError<Infallible, Infallible, Infallible>>
+  where
+    K: Clone,
+    V: Clone,
+  {
+    let db = self.db.clone();
+    self
+      .wtm
+      .commit(|ents| async move {
+        let committed = ents.to_vec();
+        db.inner.map.apply(ents);
+        Ok(committed)
+      })
+      .await
+  }
+}
+
+impl<K, V, SP, S> OptimisticTransaction<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Send + Sync + 'static,
+  V: Send + Sync + 'static,
+  S: BuildHasher + Send + Sync + 'static,
+  SP: AsyncSpawner,
+{
+  /// Acts like [`commit`](OptimisticTransaction::commit), but `f` is invoked with the final
+  /// entries and the commit timestamp after the conflict check and before the entries are
+  /// applied to the database.
+  #[inline]
+  pub async fn preview_commit_entries<F, Fut, R>(
+    &mut self,
+    f: F,
+  ) -> Result<R, WtmError<Infallible, Infallible, Infallible>>
+  where
+    F: FnOnce(&[Entry<K, V>], u64) -> Fut,
+    Fut: Future<Output = R>,
+  {
+    let db = self.db.clone();
+    self
+      .wtm
+      .preview_commit_entries(f, |ents| async move {
+        db.inner.map.apply(ents);
+        Ok(())
+      })
+      .await
+  }
 }
 
 impl<K, V, SP, S> OptimisticTransaction<K, V, SP, S>
@@ -88,9 +378,9 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. Return immediately after checking for conflicts.
-  /// If there is a conflict, an error will be returned immediately and the callback will not
-  /// run. If there are no conflicts, the callback will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    If there is a conflict, an error will be returned immediately and the callback will not
+  ///    run. If there are no conflicts, the callback will be called in the
+  ///    background upon successful completion of writes or any error during write.
   #[inline]
   pub async fn commit_with_task<Fut, E, R>(
     &mut self,
@@ -114,6 +404,52 @@ where
       )
       .await
   }
+
+  /// Commits the transaction in the background and returns the stream of the commit events,
+  /// e.g. to display the progress of a large transaction.
+  ///
+  /// The entries are applied one at a time, each followed by a [`CommitEvent::EntryApplied`],
+  /// and the stream ends with [`CommitEvent::Committed`], [`CommitEvent::Conflict`] or
+  /// [`CommitEvent::Failed`]. The commit is throttled by the polling of the stream. If the stream
+  /// is dropped before the commit starts, the transaction is discarded; once the entries are
+  /// being applied the commit can not be aborted, so the remaining entries are still applied.
+  pub async fn into_stream(mut self) -> impl Stream<Item = CommitEvent<K>>
+  where
+    K: Clone,
+  {
+    let (mut events, stream) = futures::channel::mpsc::channel(0);
+    SP::spawn_detach(async move {
+      if events.is_closed() {
+        return;
+      }
+
+      let db = self.db.clone();
+      let mut applied = events.clone();
+      let rst = self
+        .wtm
+        .preview_commit_entries(
+          |_, commit_ts| async move { commit_ts },
+          |ents| async move {
+            for ent in ents {
+              let key = ent.key().clone();
+              db.inner.map.apply(OneOrMore::from(ent));
+              let _ = applied.send(CommitEvent::EntryApplied(key)).await;
+            }
+            Ok::<_, Infallible>(())
+          },
+        )
+        .await;
+
+      let event = match rst {
+        Ok(commit_ts) => CommitEvent::Committed(commit_ts),
+        Err(WtmError::Transaction(TransactionError::Conflict)) => CommitEvent::Conflict,
+        Err(WtmError::Transaction(e)) => CommitEvent::Failed(e),
+        Err(WtmError::Commit(e)) => match e {},
+      };
+      let _ = events.send(event).await;
+    });
+    stream
+  }
 }
 
 impl<K, V, SP, S> OptimisticTransaction<K, V, SP, S>
@@ -129,6 +465,55 @@ where
     self.wtm.version()
   }
 
+  /// Waits until all the transactions with commit timestamp less than or equal to `target_ts`
+  /// are visible, or the `timeout` future completes.
+  ///
+  /// See [`AsyncWtm::wait_for_version`] for more details.
+  #[inline]
+  pub async fn wait_for_version(
+    &self,
+    target_ts: u64,
+    timeout: impl Future<Output = ()>,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.wait_for_version(target_ts, timeout).await
+  }
+
+  /// Returns [`TransactionError::StaleTransaction`] if the read version of the transaction
+  /// lags more than `max_lag` versions behind the latest committed version.
+  ///
+  /// See [`AsyncWtm::apply_if_fresh`] for more details.
+  #[inline]
+  pub async fn apply_if_fresh(
+    &self,
+    max_lag: u64,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.apply_if_fresh(max_lag).await
+  }
+
+  /// Links an external resource to the transaction, `cleanup` will be invoked with `true`
+  /// after a successful commit, or with `false` when the transaction is discarded.
+  ///
+  /// See [`AsyncWtm::link_external_resource`] for more details.
+  #[inline]
+  pub fn link_external_resource<F>(&mut self, cleanup: F)
+  where
+    F: FnOnce(bool) + Send + 'static,
+  {
+    self.wtm.link_external_resource(cleanup);
+  }
+
+  /// Registers a hook which will be invoked with the commit timestamp after the
+  /// transaction is committed successfully.
+  ///
+  /// See [`AsyncWtm::on_commit`] for more details.
+  #[inline]
+  pub fn on_commit<F>(&mut self, f: F)
+  where
+    F: FnOnce(u64) + Send + 'static,
+  {
+    self.wtm.on_commit(f);
+  }
+
   /// Rollback the transaction.
   #[inline]
   pub fn rollback(&mut self) -> Result<(), TransactionError<Infallible, Infallible>> {
@@ -179,90 +564,987 @@ where
     }
   }
 
-  /// Insert a new key-value pair.
+  /// Get the committed value from the database at the read version of this transaction,
+  /// the pending writes of this transaction are bypassed.
+  ///
+  /// This may return a different value from [`get`](OptimisticTransaction::get) if the key
+  /// has a pending write. The key is still marked as read.
   #[inline]
-  pub fn insert(
-    &mut self,
-    key: K,
-    value: V,
-  ) -> Result<(), TransactionError<Infallible, Infallible>> {
-    self.wtm.insert_blocking(key, value)
-  }
+  pub fn get_committed_only<'a, 'b: 'a, Q>(
+    &'a mut self,
+    key: &'b Q,
+  ) -> Result<Option<Ref<'a, K, V>>, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    Q: Hash + Eq + Ord + ?Sized,
+  {
+    if self.wtm.is_discard() {
+      return Err(TransactionError::Discard);
+    }
 
-  /// Remove a key.
-  #[inline]
-  pub fn remove(&mut self, key: K) -> Result<(), TransactionError<Infallible, Infallible>> {
-    self.wtm.remove_blocking(key)
+    self.wtm.mark_read_equivalent_blocking(key);
+    let version = self.wtm.version();
+    Ok(self.db.inner.map.get(key, version).map(Into::into))
   }
 
-  /// Iterate over the entries of the write transaction.
+  /// Returns `true` if the committed version of the key at the read version of this transaction
+  /// is `expected_version`, the pending writes of this transaction are bypassed.
+  ///
+  /// Returns `false` if the key does not exist. The key is marked as read, so the transaction
+  /// conflicts if the key is updated concurrently.
   #[inline]
-  pub fn iter(
+  pub fn compare_version<Q>(
     &mut self,
-  ) -> Result<TransactionIter<'_, K, V, HashCm<K, S>>, TransactionError<Infallible, Infallible>> {
-    let version = self.wtm.version();
-    let (marker, pm) = self
-      .wtm
-      .blocking_marker_with_pm()
-      .ok_or(TransactionError::Discard)?;
+    key: &Q,
+    expected_version: u64,
+  ) -> Result<bool, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    Q: Hash + Eq + Ord + ?Sized,
+  {
+    Ok(
+      self
+        .get_committed_only(key)?
+        .is_some_and(|ent| ent.version() == expected_version),
+    )
+  }
 
-    let committed = self.db.inner.map.iter(version);
-    let pendings = pm.iter();
+  /// Get the values of the keys for update, the pending writes of this transaction are taken
+  /// into account.
+  ///
+  /// All the keys are marked as both read and conflict, like a `SELECT ... FOR UPDATE`, so the
+  /// transaction conflicts with the other transactions reading or writing any of them.
+  pub fn get_many_for_update<'a>(
+    &'a mut self,
+    keys: &[K],
+  ) -> Result<Vec<Option<Ref<'a, K, V>>>, TransactionError<Infallible, Infallible>> {
+    if self.wtm.is_discard() {
+      return Err(TransactionError::Discard);
+    }
 
-    Ok(TransactionIter::new(pendings, committed, Some(marker)))
+    for key in keys {
+      self.wtm.mark_read_blocking(key);
+      self.wtm.mark_conflict_blocking(key);
+    }
+
+    let version = self.wtm.version();
+    let pm = self.wtm.pwm().ok_or(TransactionError::Discard)?;
+    let map = &self.db.inner.map;
+    keys
+      .iter()
+      .map(
+        |key| match Pwm::get_entry(pm, key).map_err(TransactionError::Pwm)? {
+          Some((k, ent)) => Ok(ent.value.as_ref().map(|v| Ref::from((ent.version, k, v)))),
+          None => Ok(map.get(key, version).map(Into::into)),
+        },
+      )
+      .collect()
   }
 
-  /// Iterate over the entries of the write transaction in reverse order.
-  #[inline]
-  pub fn iter_rev(
+  /// Folds the values of the keys with `f`, starting from `R::default()`, e.g. to sum or count
+  /// the values of a known key set without collecting them. The pending writes of this
+  /// transaction are taken into account, `f` is invoked with `None` for a missing key.
+  ///
+  /// All the keys are marked as read, including the missing ones.
+  pub fn get_multiple_and_aggregate<R, F>(
     &mut self,
-  ) -> Result<
-    WriteTransactionRevIter<'_, K, V, HashCm<K, S>>,
-    TransactionError<Infallible, Infallible>,
-  > {
-    let version = self.wtm.version();
-    let (marker, pm) = self
-      .wtm
-      .blocking_marker_with_pm()
-      .ok_or(TransactionError::Discard)?;
+    keys: &[K],
+    mut f: F,
+  ) -> Result<R, TransactionError<Infallible, Infallible>>
+  where
+    R: Default,
+    F: FnMut(R, Option<Ref<'_, K, V>>) -> R,
+  {
+    if self.wtm.is_discard() {
+      return Err(TransactionError::Discard);
+    }
 
-    let committed = self.db.inner.map.iter_rev(version);
-    let pendings = pm.iter().rev();
+    for key in keys {
+      self.wtm.mark_read_blocking(key);
+    }
 
-    Ok(WriteTransactionRevIter::new(
-      pendings,
-      committed,
-      Some(marker),
-    ))
+    let version = self.wtm.version();
+    let pm = self.wtm.pwm().ok_or(TransactionError::Discard)?;
+    let map = &self.db.inner.map;
+    keys.iter().try_fold(R::default(), |acc, key| {
+      let ent = match Pwm::get_entry(pm, key).map_err(TransactionError::Pwm)? {
+        Some((k, ent)) => ent.value.as_ref().map(|v| Ref::from((ent.version, k, v))),
+        None => map.get(key, version).map(Into::into),
+      };
+      Ok(f(acc, ent))
+    })
   }
 
-  /// Returns an iterator over the subset of entries of the database.
+  /// Get a value from the pending writes of this transaction only, the database is not read
+  /// and the key is not marked as read.
   #[inline]
-  pub fn range<'a, Q, R>(
+  pub fn get_ref(
+    &self,
+    key: &K,
+  ) -> Result<Option<Ref<'_, K, V>>, TransactionError<Infallible, Infallible>> {
+    self.wtm.get_ref(key).map(|ent| ent.map(Into::into))
+  }
+
+  /// Returns all the committed versions of the key in `[from_ts, to_ts]`, in ascending
+  /// order of version. The pending writes of this transaction are not included.
+  ///
+  /// Returns [`TransactionError::InvalidVersion`] if `from_ts > to_ts` or `to_ts` is larger
+  /// than the read version of the transaction.
+  #[inline]
+  pub fn get_multi_version<'a, Q>(
     &'a mut self,
-    range: R,
-  ) -> Result<
-    TransactionRange<'a, Q, R, K, V, HashCm<K, S>>,
-    TransactionError<Infallible, Infallible>,
-  >
+    key: &Q,
+    from_ts: u64,
+    to_ts: u64,
+  ) -> Result<Versions<'a, K, V>, TransactionError<Infallible, Infallible>>
   where
     K: Borrow<Q>,
-    R: RangeBounds<Q> + 'a,
-    Q: Ord + ?Sized,
+    Q: Hash + Eq + Ord + ?Sized,
   {
-    let version = self.wtm.version();
-    let (marker, pm) = self
-      .wtm
-      .blocking_marker_with_pm()
-      .ok_or(TransactionError::Discard)?;
-    let start = range.start_bound();
-    let end = range.end_bound();
+    if self.wtm.is_discard() {
+      return Err(TransactionError::Discard);
+    }
+
+    if from_ts > to_ts || to_ts > self.wtm.version() {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    self.wtm.mark_read_equivalent_blocking(key);
+    Ok(self.db.inner.map.versions(key, from_ts, to_ts))
+  }
+
+  /// Get a value from the database, if the key does not exist, inserts `V::default()`
+  /// to the transaction and returns it.
+  #[inline]
+  pub fn get_or_default(
+    &mut self,
+    key: K,
+  ) -> Result<Ref<'_, K, V>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    V: Default,
+  {
+    self.get_or_create(key, V::default).map(|(ent, _)| ent)
+  }
+
+  /// Get a value from the database, if the key does not exist, inserts the value created
+  /// by `create` to the transaction and returns it.
+  ///
+  /// The returned `bool` is `true` if the value was created, `create` is only called then.
+  pub fn get_or_create(
+    &mut self,
+    key: K,
+    create: impl FnOnce() -> V,
+  ) -> Result<(Ref<'_, K, V>, bool), TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    let created = !self.contains_key(&key)?;
+    if created {
+      self.insert(key.clone(), create())?;
+    }
+
+    Ok((self.get_present(&key)?, created))
+  }
+
+  /// Get a value from the database, if the key does not exist, inserts the value produced
+  /// by the future of `factory` to the transaction and returns it.
+  ///
+  /// `factory` is only called and awaited if the key does not exist.
+  pub async fn get_or_insert_with_async<F, Fut>(
+    &mut self,
+    key: K,
+    factory: F,
+  ) -> Result<Ref<'_, K, V>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = V>,
+  {
+    if !self.contains_key(&key)? {
+      let value = factory().await;
+      self.insert(key.clone(), value)?;
+    }
+
+    self.get_present(&key)
+  }
+
+  /// Get a value from the database, if the key does not exist, inserts the value computed by
+  /// the future of `compute` to the transaction and returns it.
+  ///
+  /// `compute` is only called and awaited if the key does not exist, its error is returned
+  /// as is and nothing is inserted then. The key is marked as read either way.
+  pub async fn get_or_compute<F, Fut, E>(&mut self, key: K, compute: F) -> Result<Ref<'_, K, V>, E>
+  where
+    K: Clone,
+    F: FnOnce(&K) -> Fut,
+    Fut: Future<Output = Result<V, E>>,
+    E: From<TransactionError<Infallible, Infallible>>,
+  {
+    if !self.contains_key(&key)? {
+      let value = compute(&key).await?;
+      self.insert(key.clone(), value)?;
+    }
+
+    Ok(self.get_present(&key)?)
+  }
+
+  /// Get the value of the key if its current version is `version`, otherwise inserts
+  /// `new_value`, e.g. for the ETag checks of a cache.
+  ///
+  /// The key is marked as both read and conflict regardless of the outcome.
+  pub fn get_version_or_insert(
+    &mut self,
+    key: K,
+    version: u64,
+    new_value: V,
+  ) -> Result<VersionOrInserted<'_, K, V>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    let matched = self.get(&key)?.is_some_and(|ent| ent.version() == version);
+    self.wtm.mark_conflict_blocking(&key);
+
+    if matched {
+      return self.get_present(&key).map(VersionOrInserted::Matched);
+    }
+    self.insert(key.clone(), new_value)?;
+    self.get_present(&key).map(VersionOrInserted::Inserted)
+  }
+
+  /// Get a value which is known to exist from the pending writes or the database, without
+  /// marking the key as read.
+  ///
+  /// If the transaction does not read its own writes, the database is tried first and the
+  /// pending writes are only used for a value the caller has just inserted.
+  ///
+  /// Returns [`TransactionError::Conflict`] rather than panicking if the key turns out to be
+  /// removed or missing.
+  fn get_present(
+    &self,
+    key: &K,
+  ) -> Result<Ref<'_, K, V>, TransactionError<Infallible, Infallible>> {
+    let version = self.wtm.version();
+    let pm = self.wtm.pwm().ok_or(TransactionError::Discard)?;
+    if !self.wtm.read_own_writes() {
+      if let Some(ent) = self.db.inner.map.get(key, version) {
+        return Ok(ent.into());
+      }
+    }
+
+    if let Some((k, ent)) = Pwm::get_entry(pm, key).map_err(TransactionError::Pwm)? {
+      return ent
+        .value
+        .as_ref()
+        .map(|value| Ref::from((ent.version, k, value)))
+        .ok_or(TransactionError::Conflict);
+    }
+
+    self
+      .db
+      .inner
+      .map
+      .get(key, version)
+      .map(Into::into)
+      .ok_or(TransactionError::Conflict)
+  }
+
+  /// Get a value from the database, if the key does not exist, returns the value
+  /// computed by `f` without modifying the transaction.
+  #[inline]
+  pub fn get_or_else<Q>(
+    &mut self,
+    key: &Q,
+    f: impl FnOnce() -> V,
+  ) -> Result<V, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    Q: Hash + Eq + Ord + ?Sized,
+    V: Clone,
+  {
+    Ok(match self.get(key)? {
+      Some(ent) => V::clone(&ent.value()),
+      None => f(),
+    })
+  }
+
+  /// Sets the maximum number of distinct keys the transaction can write.
+  ///
+  /// Writing a new key beyond the limit returns [`TransactionError::TooManyConflictKeys`].
+  #[inline]
+  pub fn set_conflict_keys_limit(&mut self, limit: usize) {
+    self.wtm.set_conflict_keys_limit(limit)
+  }
+
+  /// Sets whether [`get`](OptimisticTransaction::get) and
+  /// [`contains_key`](OptimisticTransaction::contains_key) see the pending writes of the
+  /// transaction, `true` by default.
+  ///
+  /// If `false`, the database is always read at the read version of the transaction.
+  #[inline]
+  pub fn set_read_own_writes(&mut self, read_own_writes: bool) {
+    self.wtm.set_read_own_writes(read_own_writes)
+  }
+
+  /// Sets the maximum number of the conflicts [`try_commit`](OptimisticTransaction::try_commit)
+  /// can retry, a conflict after that returns [`TransactionError::RetriesExhausted`].
+  #[inline]
+  pub fn set_retry_budget(&mut self, max_retries: u32) {
+    self.wtm.set_retry_budget(max_retries)
+  }
+
+  /// Returns the number of the conflicts retried by
+  /// [`try_commit`](OptimisticTransaction::try_commit).
+  #[inline]
+  pub fn retry_count(&self) -> u32 {
+    self.wtm.retry_count()
+  }
+
+  /// Sets a callback invoked with the remaining entries and the remaining size after each
+  /// successful write, once the remaining entries drop below `entries_threshold`.
+  ///
+  /// See [`AsyncWtm::set_capacity_warn_threshold`] for more details.
+  #[cfg(feature = "capacity-warn")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "capacity-warn")))]
+  #[inline]
+  pub fn set_capacity_warn_threshold<F>(&mut self, entries_threshold: u64, f: F)
+  where
+    F: Fn(u64, u64) + Send + Sync + 'static,
+  {
+    self.wtm.set_capacity_warn_threshold(entries_threshold, f)
+  }
+
+  /// Returns the estimated size of the entries to be committed, including the version
+  /// overhead of each entry.
+  #[inline]
+  pub fn estimate_total_commit_size(&self) -> u64 {
+    self.wtm.estimate_total_commit_size()
+  }
+
+  /// Drains the pending removes of the transaction and returns their keys, the pending
+  /// inserts are left in the transaction.
+  #[inline]
+  pub fn drain_removes(&mut self) -> Result<Vec<K>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    self.wtm.drain_removes_blocking()
+  }
+
+  /// Drains the pending writes of the transaction, e.g. to move them into another transaction,
+  /// the transaction stays live and can accept new writes.
+  ///
+  /// The versions of the entries are the read version of the transaction.
+  #[inline]
+  pub fn pending_entries_drain(
+    &mut self,
+  ) -> Result<Vec<Entry<K, V>>, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    self
+      .wtm
+      .pending_entries_drain_blocking()
+      .map(|entries| entries.into_iter().collect())
+  }
+
+  /// Moves the pending writes of this transaction into the target transaction of the same
+  /// database, the reads and the conflict keys of this transaction are merged into the target
+  /// so the target conflicts whenever this transaction would. This transaction is left with
+  /// empty pending writes.
+  ///
+  /// See [`AsyncWtm::merge_pending_into_blocking`] for the errors.
+  pub fn merge_pending_into(
+    &mut self,
+    target: &mut Self,
+  ) -> Result<(), TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    self.wtm.merge_pending_into_blocking(&mut target.wtm)
+  }
+
+  /// Consumes the transaction and returns its write set without committing it, e.g. to ship
+  /// the writes to another replica.
+  ///
+  /// The versions of the entries are the read version of the transaction.
+  #[inline]
+  pub fn into_entries(self) -> Result<Vec<Entry<K, V>>, TransactionError<Infallible, Infallible>> {
+    self
+      .wtm
+      .into_entries_blocking()
+      .map(|entries| entries.into_iter().collect())
+  }
+
+  /// Returns the maximum number of entries of the transaction, 0 if it has been committed.
+  #[inline]
+  pub fn max_batch_entries(&self) -> u64 {
+    self.wtm.max_batch_entries()
+  }
+
+  /// Returns the maximum size of the transaction, 0 if it has been committed.
+  #[inline]
+  pub fn max_batch_size(&self) -> u64 {
+    self.wtm.max_batch_size()
+  }
+
+  /// Returns the number of entries can still be written before the transaction
+  /// becomes too large.
+  #[inline]
+  pub fn remaining_entries(&self) -> u64 {
+    self.wtm.remaining_entries()
+  }
+
+  /// Returns the size left of the transaction, an entry can still be written if its estimated
+  /// size is not larger than this.
+  #[inline]
+  pub fn remaining_size(&self) -> u64 {
+    self.wtm.remaining_size()
+  }
+
+  /// Returns the number of keys with a pending insert, a key inserted many times is counted once.
+  #[inline]
+  pub fn pending_inserts_count(&self) -> usize {
+    self.wtm.pending_inserts_count_blocking()
+  }
+
+  /// Returns the number of keys with a pending remove.
+  #[inline]
+  pub fn pending_removes_count(&self) -> usize {
+    self.wtm.pending_removes_count_blocking()
+  }
+
+  /// Checks that no write of the transaction overwrote a pending write of the same key,
+  /// returns [`TransactionError::DuplicateKey`] otherwise, even if the key is retracted
+  /// afterwards.
+  #[inline]
+  pub fn guarantee_unique_keys(&self) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.guarantee_unique_keys()
+  }
+
+  /// Returns the number of the older versions of the keys written in managed mode.
+  #[inline]
+  pub fn duplicate_write_count(&self) -> usize {
+    self.wtm.duplicate_write_count()
+  }
+
+  /// Returns the number of entries will be committed, including the duplicate writes.
+  #[inline]
+  pub fn total_entry_count(&self) -> usize {
+    self.wtm.total_entry_count_blocking()
+  }
+
+  /// Returns an iterator over the fingerprints of the keys written by this transaction,
+  /// `None` if the transaction has been discarded.
+  #[inline]
+  pub fn conflict_keys_iter(&self) -> Option<impl Iterator<Item = u64> + '_> {
+    self.wtm.cm().map(HashCm::conflict_keys_iter)
+  }
+
+  /// Returns the number of the keys written by this transaction in the range, which are
+  /// tracked as conflict keys, `None` if the transaction has been discarded.
+  ///
+  /// The conflict manager only keeps the fingerprints of the keys, so the keys marked as
+  /// conflict without being written, e.g. by
+  /// [`get_many_for_update`](OptimisticTransaction::get_many_for_update), are not counted.
+  pub fn count_conflict_keys_in_range<R>(&self, range: R) -> Option<usize>
+  where
+    R: RangeBounds<K>,
+  {
+    self.wtm.cm()?;
+    self.wtm.pwm().map(|pm| PwmRange::range(pm, range).count())
+  }
+
+  /// Returns an iterator over the fingerprints of the keys read by this transaction.
+  #[inline]
+  pub fn reads_iter(&self) -> impl Iterator<Item = u64> + '_ {
+    self.wtm.cm().into_iter().flat_map(HashCm::reads_iter)
+  }
+
+  /// Get a value from the database as a reference-counted handle, which can outlive
+  /// the transaction.
+  ///
+  /// The pinned value is a snapshot, later writes to the key are not reflected in it.
+  #[inline]
+  pub fn pin_value<Q>(
+    &mut self,
+    key: &Q,
+  ) -> Result<Option<Arc<V>>, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    Q: Hash + Eq + Ord + ?Sized,
+    V: Clone,
+  {
+    Ok(self.get(key)?.map(|ent| Arc::new(V::clone(&ent.value()))))
+  }
+
+  /// Insert a new key-value pair.
+  #[inline]
+  pub fn insert(
+    &mut self,
+    key: K,
+    value: V,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.insert_blocking(key, value)
+  }
+
+  /// Insert the key-value pairs with their pre-computed fingerprints, skipping the hashing
+  /// of the keys for conflict detection.
+  ///
+  /// # Warning
+  /// Every fingerprint must be the one [`OptimisticDb::fingerprint`] returns for its key,
+  /// otherwise conflicts on the key will silently go undetected.
+  #[inline]
+  pub fn insert_batch_raw(
+    &mut self,
+    entries: impl IntoIterator<Item = (K, V, u64)>,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.insert_batch_raw_blocking(entries)
+  }
+
+  /// Inserts the pre-built entries in order, e.g. the entries decoded from a WAL, see
+  /// [`AsyncWtm::insert_many_raw_blocking`].
+  #[inline]
+  pub fn insert_many_raw(
+    &mut self,
+    entries: impl IntoIterator<Item = Entry<K, V>>,
+  ) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.insert_many_raw_blocking(entries)
+  }
+
+  /// Insert a new key-value pair, and returns the old value of the key if any.
+  ///
+  /// The key is marked as both read and conflict.
+  #[inline]
+  pub fn replace(
+    &mut self,
+    key: K,
+    value: V,
+  ) -> Result<Option<V>, TransactionError<Infallible, Infallible>>
+  where
+    V: Clone,
+  {
+    let old = self.get(&key)?.map(|ent| V::clone(&ent.value()));
+    self.insert(key, value)?;
+    Ok(old)
+  }
+
+  /// Inserts the value produced by `f` for each of the keys, `f` is called with the key and
+  /// its current value if any.
+  ///
+  /// The keys are marked as both read and conflict. If a key appears more than once, later
+  /// calls of `f` see the value written by the earlier ones.
+  pub fn upsert_batch<F>(
+    &mut self,
+    keys: Vec<K>,
+    f: F,
+  ) -> Result<(), TransactionError<Infallible, Infallible>>
+  where
+    F: Fn(&K, Option<&V>) -> V,
+  {
+    for key in keys {
+      let value = match self.get(&key)? {
+        Some(ent) => f(&key, Some(&*ent.value())),
+        None => f(&key, None),
+      };
+      self.insert(key, value)?;
+    }
+    Ok(())
+  }
+
+  /// Swaps the values of the two keys within this transaction, if only one of the keys
+  /// exists, its value is moved to the other key.
+  ///
+  /// Returns whether `key_a` and `key_b` existed. Both keys are marked as read and conflict.
+  /// If `key_a` and `key_b` are the same key, nothing is written, but the key is still marked
+  /// as both read and conflict.
+  pub fn atomic_swap(
+    &mut self,
+    key_a: K,
+    key_b: K,
+  ) -> Result<(bool, bool), TransactionError<Infallible, Infallible>>
+  where
+    V: Clone,
+  {
+    let a = self.get(&key_a)?.map(|ent| V::clone(&ent.value()));
+    if key_a == key_b {
+      self.wtm.mark_conflict_blocking(&key_a);
+      let exists = a.is_some();
+      return Ok((exists, exists));
+    }
+    let b = self.get(&key_b)?.map(|ent| V::clone(&ent.value()));
+    let exists = (a.is_some(), b.is_some());
+
+    match b {
+      Some(value) => self.insert(key_a, value)?,
+      None => self.remove(key_a)?,
+    }
+    match a {
+      Some(value) => self.insert(key_b, value)?,
+      None => self.remove(key_b)?,
+    }
+    Ok(exists)
+  }
+
+  /// Moves the value of `old_key` to `new_key` within this transaction, `old_key` is removed.
+  ///
+  /// Returns `false` if `old_key` does not exist, in which case nothing is written.
+  /// `old_key` is marked as both read and conflict, `new_key` is marked as conflict.
+  #[inline]
+  pub fn rename_key(
+    &mut self,
+    old_key: K,
+    new_key: K,
+  ) -> Result<bool, TransactionError<Infallible, Infallible>>
+  where
+    V: Clone,
+  {
+    let value = match self.get(&old_key)? {
+      Some(ent) => V::clone(&ent.value()),
+      None => return Ok(false),
+    };
+
+    if old_key == new_key {
+      return Ok(true);
+    }
+
+    self.insert(new_key, value)?;
+    self.remove(old_key)?;
+    Ok(true)
+  }
+
+  /// Remove a key.
+  #[inline]
+  pub fn remove(&mut self, key: K) -> Result<(), TransactionError<Infallible, Infallible>> {
+    self.wtm.remove_blocking(key)
+  }
+
+  /// Removes the key only if its current value equals `expected`, `None` means the key
+  /// does not exist, returns `true` if the key is removed.
+  ///
+  /// A missing key matches `None`, but there is nothing to remove, so `false` is returned.
+  ///
+  /// The key is marked as both read and conflict regardless of the outcome.
+  pub fn conditional_remove(
+    &mut self,
+    key: K,
+    expected: Option<&V>,
+  ) -> Result<bool, TransactionError<Infallible, Infallible>>
+  where
+    V: PartialEq,
+  {
+    let (matched, exists) = match self.get(&key)? {
+      Some(ent) => (expected == Some(&*ent.value()), true),
+      None => (expected.is_none(), false),
+    };
+    self.wtm.mark_conflict_blocking(&key);
+
+    let removed = matched && exists;
+    if removed {
+      self.remove(key)?;
+    }
+    Ok(removed)
+  }
+
+  /// Retracts the pending write of the key, without adding a delete marker for it.
+  ///
+  /// Returns `true` if the key was in the pending writes, `false` otherwise.
+  #[inline]
+  pub fn revert_key(&mut self, key: &K) -> Result<bool, TransactionError<Infallible, Infallible>> {
+    self.wtm.revert_key_blocking(key)
+  }
+
+  /// Applies `f` to the value of every pending insert in place, pending removes are left untouched.
+  #[inline]
+  pub fn map_values<F>(&mut self, f: F) -> Result<(), TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    F: FnMut(&K, V) -> V,
+  {
+    self.wtm.map_values_blocking(f)
+  }
+
+  /// Retracts the pending writes of the keys in the range, without adding delete markers
+  /// for them, returns the number of the retracted keys.
+  #[inline]
+  pub fn remove_pending_range<R>(
+    &mut self,
+    range: R,
+  ) -> Result<usize, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+    R: RangeBounds<K>,
+  {
+    self.wtm.remove_pending_range_blocking(range)
+  }
+
+  /// Iterate over the entries of the write transaction.
+  #[inline]
+  pub fn iter(
+    &mut self,
+  ) -> Result<TransactionIter<'_, K, V, HashCm<K, S>>, TransactionError<Infallible, Infallible>> {
+    let version = self.wtm.version();
+    let (marker, pm) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+
+    let committed = self.db.inner.map.iter(version);
+    let pendings = pm.iter();
+
+    Ok(TransactionIter::new(pendings, committed, Some(marker)))
+  }
+
+  /// Iterate over the entries of the write transaction with the given options, see
+  /// [`iter`](OptimisticTransaction::iter).
+  ///
+  /// With [`IteratorOptions::exclude_pending`], only the committed entries at the read version
+  /// are yielded, like [`set_read_own_writes(false)`](OptimisticTransaction::set_read_own_writes)
+  /// does for the point reads. The yielded keys are still marked as read.
+  ///
+  /// With [`IteratorOptions::with_version_range`], returns [`TransactionError::InvalidVersion`]
+  /// if the lowest version is larger than the highest one, the highest version is larger than
+  /// the read version of the transaction, or the versions at the highest version may have been
+  /// discarded by compaction.
+  pub fn iter_with_options(
+    &mut self,
+    opts: IteratorOptions,
+  ) -> Result<TransactionIter<'_, K, V, HashCm<K, S>>, TransactionError<Infallible, Infallible>> {
+    if !opts.exclude_pending && !opts.is_version_filtered() {
+      return self.iter();
+    }
+
+    let version = self.wtm.version();
+    let (from, to) = (
+      opts.version_from.unwrap_or(0),
+      opts.version_to.unwrap_or(version),
+    );
+    let map = &self.db.inner.map;
+    if opts.is_version_filtered() && (from > to || to > version || to < map.last_discard_version())
+    {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    let (marker, _) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+
+    let committed = map.iter_window(from, to);
+    Ok(TransactionIter::committed_only(committed, Some(marker)))
+  }
+
+  /// Iterate over the values of the write transaction, see [`iter`](OptimisticTransaction::iter).
+  ///
+  /// The values are cloned, as a committed entry can only be borrowed while it is yielded.
+  #[inline]
+  pub fn iter_values_only(
+    &mut self,
+  ) -> Result<impl Iterator<Item = V> + '_, TransactionError<Infallible, Infallible>>
+  where
+    V: Clone,
+  {
+    self
+      .iter()
+      .map(|iter| iter.map(|ent| V::clone(&ent.value())))
+  }
+
+  /// Iterate over the keys of the write transaction, see [`iter`](OptimisticTransaction::iter).
+  ///
+  /// The keys are cloned, as a committed entry can only be borrowed while it is yielded.
+  #[inline]
+  pub fn iter_keys_only(
+    &mut self,
+  ) -> Result<impl Iterator<Item = K> + '_, TransactionError<Infallible, Infallible>>
+  where
+    K: Clone,
+  {
+    self.iter().map(|iter| iter.map(|ent| ent.key().clone()))
+  }
+
+  /// Iterate over the entries of the write transaction in reverse order.
+  #[inline]
+  pub fn iter_rev(
+    &mut self,
+  ) -> Result<
+    WriteTransactionRevIter<'_, K, V, HashCm<K, S>>,
+    TransactionError<Infallible, Infallible>,
+  > {
+    let version = self.wtm.version();
+    let (marker, pm) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+
+    let committed = self.db.inner.map.iter_rev(version);
+    let pendings = pm.iter().rev();
+
+    Ok(WriteTransactionRevIter::new(
+      pendings,
+      committed,
+      Some(marker),
+    ))
+  }
+
+  /// Returns an iterator over the subset of entries of the database.
+  #[inline]
+  pub fn range<'a, Q, R>(
+    &'a mut self,
+    range: R,
+  ) -> Result<
+    TransactionRange<'a, Q, R, K, V, HashCm<K, S>>,
+    TransactionError<Infallible, Infallible>,
+  >
+  where
+    K: Borrow<Q>,
+    R: RangeBounds<Q> + 'a,
+    Q: Ord + ?Sized,
+  {
+    let version = self.wtm.version();
+    let (marker, pm) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+    let start = range.start_bound();
+    let end = range.end_bound();
     let pendings = pm.range_comparable((start, end));
     let committed = self.db.inner.map.range(range, version);
 
     Ok(TransactionRange::new(pendings, committed, Some(marker)))
   }
 
+  /// Acts like [`range`](OptimisticTransaction::range), but takes the lower and upper bounds
+  /// explicitly, e.g. `(Bound::Excluded(start), Bound::Included(end))`.
+  #[inline]
+  pub fn iter_from_to(
+    &mut self,
+    bounds: (Bound<K>, Bound<K>),
+  ) -> Result<
+    TransactionRange<'_, K, (Bound<K>, Bound<K>), K, V, HashCm<K, S>>,
+    TransactionError<Infallible, Infallible>,
+  > {
+    self.range(bounds)
+  }
+
+  /// Returns `true` if any key in the range exists, the pending writes of this transaction
+  /// are taken into account.
+  ///
+  /// As [`HashCm`] cannot track a key range, the whole database is marked as read, so that a
+  /// key inserted into the range concurrently is detected at commit time. In other words, this
+  /// transaction conflicts with any other transaction committed after it started.
+  #[inline]
+  pub fn contains_range<Q, R>(
+    &mut self,
+    range: R,
+  ) -> Result<bool, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+  {
+    self
+      .wtm
+      .blocking_marker()
+      .ok_or(TransactionError::Discard)?
+      .mark_iter();
+    Ok(self.range(range)?.next().is_some())
+  }
+
+  /// Returns the number of keys in the range, the pending inserts and removes of this
+  /// transaction are taken into account.
+  ///
+  /// As [`contains_range`](OptimisticTransaction::contains_range), the whole database is
+  /// marked as read.
+  pub fn get_range_count<Q, R>(
+    &mut self,
+    range: R,
+  ) -> Result<u64, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<Q>,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+  {
+    let version = self.wtm.version();
+    let (mut marker, pm) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+    marker.mark_iter();
+
+    let start = range.start_bound();
+    let end = range.end_bound();
+    let map = &self.db.inner.map;
+    let mut count = map.count_range((start, end), version);
+    for (k, v) in pm.range_comparable((start, end)) {
+      match (v.value.is_some(), map.contains_key::<K>(k, version)) {
+        (true, false) => count += 1,
+        (false, true) => count -= 1,
+        _ => {}
+      }
+    }
+    Ok(count)
+  }
+
+  /// Returns the number of all the keys, the pending inserts and removes of this transaction
+  /// are taken into account, i.e. a pending insert of an existing key is not counted twice.
+  ///
+  /// The committed keys are counted by a scan. As
+  /// [`get_range_count`](OptimisticTransaction::get_range_count) and
+  /// [`scan_prefix_count`](OptimisticTransaction::scan_prefix_count), the whole database is
+  /// marked as read, so this transaction conflicts with every concurrent write transaction
+  /// which commits before it. Count with a [`ReadTransaction`] if that is not desired.
+  pub fn count_all(&mut self) -> Result<u64, TransactionError<Infallible, Infallible>> {
+    let version = self.wtm.version();
+    let (mut marker, pm) = self
+      .wtm
+      .blocking_marker_with_pm()
+      .ok_or(TransactionError::Discard)?;
+    marker.mark_iter();
+
+    let map = &self.db.inner.map;
+    let mut count = map.key_count(version);
+    for (k, v) in pm.iter() {
+      match (v.value.is_some(), map.contains_key(k, version)) {
+        (true, false) => count += 1,
+        (false, true) => count -= 1,
+        _ => {}
+      }
+    }
+    Ok(count)
+  }
+
+  /// Returns the number of keys starting with `prefix`, the pending inserts and removes of
+  /// this transaction are taken into account.
+  ///
+  /// As [`get_range_count`](OptimisticTransaction::get_range_count), the whole database is
+  /// marked as read.
+  pub fn scan_prefix_count(
+    &mut self,
+    prefix: &[u8],
+  ) -> Result<u64, TransactionError<Infallible, Infallible>>
+  where
+    K: Borrow<[u8]>,
+  {
+    let upper = prefix_upper_bound(prefix);
+    self.get_range_count(prefix_range(prefix, upper.as_deref()))
+  }
+
+  /// Returns the number of the committed keys starting with `prefix` at the read version of
+  /// this transaction, the pending writes are bypassed and nothing is marked as read.
+  ///
+  /// The count may be stale, so it is only suitable for non-critical use, e.g. monitoring.
+  pub fn scan_prefix_count_approx(&self, prefix: &[u8]) -> u64
+  where
+    K: Borrow<[u8]>,
+  {
+    let upper = prefix_upper_bound(prefix);
+    self
+      .db
+      .inner
+      .map
+      .count_range(prefix_range(prefix, upper.as_deref()), self.wtm.version())
+  }
+
   /// Returns an iterator over the subset of entries of the database in reverse order.
   #[inline]
   pub fn range_rev<'a, Q, R>(
@@ -294,3 +1576,112 @@ where
     ))
   }
 }
+
+#[cfg(feature = "parallel-reads")]
+#[cfg_attr(docsrs, doc(cfg(feature = "parallel-reads")))]
+impl<K, V, SP, S> OptimisticTransaction<K, V, SP, S>
+where
+  K: Ord + Hash + Eq + Clone + Send + Sync + 'static,
+  V: Clone + Send + Sync + 'static,
+  S: BuildHasher + Clone + Send + Sync + 'static,
+  SP: AsyncSpawner,
+{
+  /// Gets the values of the keys, the keys missing from the pending writes are split into
+  /// chunks and looked up in the database concurrently by at most `max_concurrency` spawned
+  /// tasks, one task per chunk.
+  ///
+  /// All the keys are marked as read before any lookup is spawned. The values are cloned,
+  /// as the spawned tasks can not borrow the database. Returns
+  /// [`TransactionError::Canceled`] if a task is dropped before it finishes.
+  pub async fn parallel_get(
+    &mut self,
+    keys: &[K],
+    max_concurrency: usize,
+  ) -> Result<Vec<Option<V>>, TransactionError<Infallible, Infallible>> {
+    if self.wtm.is_discard() {
+      return Err(TransactionError::Discard);
+    }
+
+    for key in keys {
+      self.wtm.mark_read_blocking(key);
+    }
+
+    let version = self.wtm.version();
+    let read_own_writes = self.wtm.read_own_writes();
+    let pm = self.wtm.pwm().ok_or(TransactionError::Discard)?;
+    let mut values = Vec::with_capacity(keys.len());
+    let mut misses = Vec::new();
+    for (idx, key) in keys.iter().enumerate() {
+      let pending = if read_own_writes {
+        Pwm::get(pm, key).map_err(TransactionError::Pwm)?
+      } else {
+        None
+      };
+      match pending {
+        Some(ent) => values.push(ent.value.clone()),
+        None => {
+          values.push(None);
+          misses.push(idx);
+        }
+      }
+    }
+
+    if misses.is_empty() {
+      return Ok(values);
+    }
+
+    let chunk_size = misses.len().div_ceil(max_concurrency.max(1));
+    let chunks = misses.chunks(chunk_size).collect::<Vec<_>>();
+    let lookups = chunks
+      .iter()
+      .map(|chunk| {
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let db = self.db.clone();
+        let keys = chunk
+          .iter()
+          .map(|&idx| keys[idx].clone())
+          .collect::<Vec<_>>();
+        SP::spawn_detach(async move {
+          let values = keys
+            .iter()
+            .map(|key| {
+              db.inner
+                .map
+                .get(key, version)
+                .map(|ent| V::clone(&ent.value()))
+            })
+            .collect::<Vec<_>>();
+          let _ = tx.send(values);
+        });
+        rx
+      })
+      .collect::<Vec<_>>();
+
+    for (chunk, found) in chunks.iter().zip(futures::future::join_all(lookups).await) {
+      let found = found.map_err(|_| TransactionError::Canceled)?;
+      for (&idx, value) in chunk.iter().zip(found) {
+        values[idx] = value;
+      }
+    }
+    Ok(values)
+  }
+}
+
+/// Returns the smallest key greater than all the keys starting with `prefix`,
+/// `None` if there is no such key.
+fn prefix_upper_bound(prefix: &[u8]) -> Option<Vec<u8>> {
+  let end = prefix.iter().rposition(|b| *b != u8::MAX)?;
+  let mut upper = prefix[..=end].to_vec();
+  upper[end] += 1;
+  Some(upper)
+}
+
+fn prefix_range<'a>(
+  prefix: &'a [u8],
+  upper: Option<&'a [u8]>,
+) -> (Bound<&'a [u8]>, Bound<&'a [u8]>) {
+  (
+    Bound::Included(prefix),
+    upper.map_or(Bound::Unbounded, Bound::Excluded),
+  )
+}
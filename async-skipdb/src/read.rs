@@ -1,3 +1,5 @@
+use std::convert::Infallible;
+
 use skipdb_core::rev_range::RevRange;
 
 use super::*;
@@ -92,4 +94,24 @@ where
     let version = self.rtm.version();
     self.db.as_inner().range_rev(range, version)
   }
+
+  /// Returns an iterator over all the committed versions in `(since_version, version]`,
+  /// where `version` is the version of this transaction, in ascending order of key, and
+  /// descending order of version for the same key. The removed versions are also yielded.
+  ///
+  /// This is useful for incremental sync (e.g. CDC subscribers), the versions stay available
+  /// while this transaction is alive. Returns [`TransactionError::InvalidVersion`] if the
+  /// versions after `since_version` may have been discarded by compaction.
+  #[inline]
+  pub fn entries_since_version(
+    &self,
+    since_version: u64,
+  ) -> Result<VersionIter<'_, K, V>, TransactionError<Infallible, Infallible>> {
+    let inner = self.db.as_inner();
+    if since_version.saturating_add(1) < inner.last_discard_version() {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    Ok(inner.iter_versions(since_version.saturating_add(1), self.rtm.version()))
+  }
 }
@@ -10,9 +10,9 @@
 
 use std::sync::Arc;
 
-use core::mem;
+use core::{future::Future, mem};
 
-use error::TransactionError;
+use error::{OracleError, TransactionError, WtmError};
 pub use smallvec_wrapper::OneOrMore;
 
 pub use wmark::{AsyncSpawner, Detach};
@@ -38,6 +38,8 @@ mod read;
 pub use read::*;
 mod write;
 pub use write::*;
+mod state;
+pub use state::*;
 
 pub use txn_core::{
   future::*,
@@ -57,6 +59,20 @@ where
   _phantom: std::marker::PhantomData<(K, V, P)>,
 }
 
+/// A snapshot of the aggregate counters of a transaction manager.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransactionStats {
+  /// The number of write transactions committed successfully.
+  pub total_commits: u64,
+  /// The number of write transactions failed to commit because of a conflict.
+  pub total_conflicts: u64,
+  /// The number of write transactions discarded without being committed,
+  /// including the ones failed to commit.
+  pub total_discards: u64,
+  /// The number of `get*` calls on write transactions.
+  pub total_reads: u64,
+}
+
 impl<K, V, C, P, S> Clone for AsyncTm<K, V, C, P, S>
 where
   S: AsyncSpawner,
@@ -83,7 +99,7 @@ where
     conflict_manager_opts: C::Options,
   ) -> Result<AsyncWtm<K, V, C, P, S>, TransactionError<C::Error, P::Error>> {
     let read_ts = self.inner.read_ts().await;
-    Ok(AsyncWtm {
+    let wtm = AsyncWtm {
       orc: self.inner.clone(),
       read_ts,
       size: 0,
@@ -101,7 +117,188 @@ where
       duplicate_writes: OneOrMore::new(),
       discarded: false,
       done_read: false,
-    })
+      cleanups: Vec::new(),
+      commit_hooks: Vec::new(),
+      committed: false,
+      conflict_keys_limit: None,
+      read_own_writes: true,
+      overwrites: 0,
+      retry_budget: None,
+      retry_count: 0,
+      #[cfg(feature = "capacity-warn")]
+      capacity_warn: None,
+    };
+    // dropping the refused transaction discards it.
+    if !self.inner.begin_txn() {
+      return Err(TransactionError::Closed);
+    }
+    Ok(wtm)
+  }
+}
+
+impl<K, V, C, P, S> AsyncTm<K, V, C, P, S>
+where
+  C: AsyncCm<Key = K>,
+  P: AsyncPwm<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Creates a new write transaction with a fresh read version from the pending writes of a
+  /// discarded (but not committed) transaction, e.g. one discarded because of a transient error.
+  ///
+  /// The reads of the discarded transaction are not tracked by the new transaction, only its
+  /// writes are. Returns [`TransactionError::StaleTransaction`] if the read version of the
+  /// discarded transaction lags more than `max_staleness` versions behind the new one, and
+  /// [`TransactionError::Discard`] if the transaction has been committed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the transaction is not created by this transaction manager.
+  pub async fn absorb_discard(
+    &self,
+    mut discarded: AsyncWtm<K, V, C, P, S>,
+    max_staleness: u64,
+    conflict_manager_opts: C::Options,
+  ) -> Result<AsyncWtm<K, V, C, P, S>, TransactionError<C::Error, P::Error>> {
+    assert!(
+      Arc::ptr_eq(&discarded.orc, &self.inner),
+      "the transaction is not created by this transaction manager"
+    );
+
+    discarded.discard();
+    let pending_writes = discarded
+      .pending_writes
+      .take()
+      .ok_or(TransactionError::Discard)?;
+
+    let mut conflict_manager = C::new(conflict_manager_opts)
+      .await
+      .map_err(TransactionError::conflict)?;
+    for (key, _) in pending_writes.iter().await {
+      conflict_manager.mark_conflict(key).await;
+    }
+
+    let read_ts = self.inner.read_ts().await;
+    let wtm = AsyncWtm {
+      orc: self.inner.clone(),
+      read_ts,
+      size: discarded.size,
+      count: discarded.count,
+      conflict_manager: Some(conflict_manager),
+      pending_writes: Some(pending_writes),
+      duplicate_writes: mem::take(&mut discarded.duplicate_writes),
+      discarded: false,
+      done_read: false,
+      cleanups: Vec::new(),
+      commit_hooks: mem::take(&mut discarded.commit_hooks),
+      committed: false,
+      conflict_keys_limit: discarded.conflict_keys_limit,
+      read_own_writes: discarded.read_own_writes,
+      overwrites: discarded.overwrites,
+      retry_budget: discarded.retry_budget,
+      retry_count: discarded.retry_count,
+      #[cfg(feature = "capacity-warn")]
+      capacity_warn: discarded.capacity_warn.take(),
+    };
+    if !self.inner.begin_txn() {
+      return Err(TransactionError::Closed);
+    }
+
+    // dropping the new transaction releases its read version.
+    let lag = read_ts.saturating_sub(discarded.read_ts);
+    if lag > max_staleness {
+      return Err(TransactionError::StaleTransaction { lag });
+    }
+
+    Ok(wtm)
+  }
+
+  /// Commits a batch of write transactions created by this transaction manager atomically,
+  /// returns the commit timestamp of each transaction.
+  ///
+  /// The transactions are checked for conflicts in order, a transaction also conflicts with
+  /// the previous ones in the batch. If any transaction conflicts, none of them is committed.
+  /// Otherwise, `apply` is invoked once with the entries of all the transactions, in order.
+  ///
+  /// Returns [`TransactionError::InvalidVersion`] if the commit timestamps of the batch would
+  /// overflow, in which case none of the transactions is committed.
+  ///
+  /// # Panics
+  ///
+  /// Panics if any transaction is not created by this transaction manager.
+  pub async fn commit_batch<F, Fut, E>(
+    &self,
+    mut txns: Vec<AsyncWtm<K, V, C, P, S>>,
+    apply: F,
+  ) -> Result<Vec<u64>, WtmError<C::Error, P::Error, E>>
+  where
+    Fut: Future<Output = Result<(), E>>,
+    F: FnOnce(Vec<OneOrMore<Entry<K, V>>>) -> Fut,
+    E: std::error::Error,
+  {
+    for txn in txns.iter() {
+      assert!(
+        Arc::ptr_eq(&txn.orc, &self.inner),
+        "the transaction is not created by this transaction manager"
+      );
+
+      if txn.discarded {
+        return Err(WtmError::transaction(TransactionError::Discard));
+      }
+    }
+
+    let (timestamps, entries) = {
+      // Same as the single transaction commit, hold the lock until all the entries are collected.
+      let _write_lock = self.inner.write_serialize_lock.lock().await;
+
+      let mut batch = txns
+        .iter_mut()
+        .map(|txn| {
+          (
+            &mut txn.done_read,
+            txn.read_ts,
+            mem::take(&mut txn.conflict_manager),
+          )
+        })
+        .collect::<Vec<_>>();
+
+      let timestamps = match self.inner.new_commit_ts_batch(&mut batch).await {
+        Ok(timestamps) => timestamps,
+        // the transactions will be discarded when dropped.
+        Err(CreateCommitTimestampsError::Conflict) => {
+          self.inner.stats.record_conflict();
+          return Err(WtmError::transaction(TransactionError::Conflict));
+        }
+        Err(CreateCommitTimestampsError::InvalidVersion) => {
+          return Err(WtmError::transaction(TransactionError::InvalidVersion));
+        }
+      };
+
+      let mut entries = Vec::with_capacity(txns.len());
+      for (txn, ts) in txns.iter_mut().zip(timestamps.iter()) {
+        entries.push(txn.take_entries(*ts).await);
+      }
+      (timestamps, entries)
+    };
+
+    match apply(entries).await {
+      Ok(()) => {
+        for (txn, ts) in txns.iter_mut().zip(timestamps.iter()) {
+          run_commit_hooks(mem::take(&mut txn.commit_hooks), *ts);
+          self.inner.done_commit(*ts);
+          txn.mark_committed();
+          txn.run_cleanups(true);
+          txn.discard();
+        }
+        Ok(timestamps)
+      }
+      Err(e) => {
+        for (txn, ts) in txns.iter_mut().zip(timestamps.iter()) {
+          self.inner.done_commit(*ts);
+          txn.discard();
+        }
+        Err(WtmError::commit(e))
+      }
+    }
   }
 }
 
@@ -133,14 +330,69 @@ where
   /// Returns the current read version of the database.
   #[inline]
   pub async fn version(&self) -> u64 {
-    self.inner.read_ts().await
+    let version = self.inner.read_ts().await;
+    // nothing is read at the version, so it must not stay active.
+    self.inner.done_read(version);
+    version
+  }
+
+  /// Advances the current version of the transaction manager to `max(current, new_ts)`.
+  ///
+  /// This is useful to synchronize the clock (e.g. HLC timestamps) in distributed deployments.
+  /// Returns [`OracleError::Overflow`] if `new_ts` is `u64::MAX`.
+  #[inline]
+  pub async fn force_advance(&self, new_ts: u64) -> Result<(), OracleError> {
+    self.inner.force_advance(new_ts).await
   }
 
-  /// Close the transaction manager.
+  /// Seeds the current version of the transaction manager, e.g. with the last committed
+  /// version recovered from a WAL.
+  ///
+  /// Returns [`OracleError::ActiveTransactions`] if any read or write transaction is active, and
+  /// [`OracleError::InvalidVersion`] if `version` is not greater than the current version.
+  #[inline]
+  pub async fn set_initial_version(&self, version: u64) -> Result<(), OracleError> {
+    self.inner.set_initial_version(version).await
+  }
+
+  /// Allocates a single commit timestamp for a bulk import and invokes `apply` with it,
+  /// bypassing the conflict detection entirely, returns the commit timestamp.
+  ///
+  /// This is for offline or initialization use only, it is **NOT** safe to call concurrently
+  /// with live write transactions, as they will not conflict with the imported data.
+  ///
+  /// Returns [`OracleError::Overflow`] if the commit timestamp would overflow, in which case
+  /// `apply` is not invoked.
+  pub async fn import<F, Fut, E>(&self, apply: F) -> Result<u64, E>
+  where
+    F: FnOnce(u64) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: From<OracleError>,
+  {
+    let _write_lock = self.inner.write_serialize_lock.lock().await;
+    let commit_ts = self.inner.new_commit_ts_unchecked().await?;
+    let rst = apply(commit_ts).await;
+    self.inner.done_commit(commit_ts);
+    rst.map(|_| commit_ts)
+  }
+
+  /// Close the transaction manager, no write transaction can be created afterwards.
   #[inline]
   pub async fn close(&self) {
     self.inner.stop().await;
   }
+
+  /// Close the transaction manager gracefully, no write transaction can be created afterwards,
+  /// and the transaction manager is stopped after all the active write transactions are
+  /// committed or discarded.
+  ///
+  /// Returns [`OracleError::Timeout`] if the `grace` future completes first, the transaction
+  /// manager is then closed but not stopped, so the active transactions can still finish.
+  pub async fn close_gracefully(&self, grace: impl Future<Output = ()>) -> Result<(), OracleError> {
+    self.inner.drain(grace).await?;
+    self.inner.stop().await;
+    Ok(())
+  }
 }
 
 impl<K, V, C, P, S> AsyncTm<K, V, C, P, S>
@@ -153,6 +405,48 @@ where
     self.inner.discard_at_or_below()
   }
 
+  /// Returns the maximum read version of the transactions which are not done yet,
+  /// or 0 if there is none. This is useful to detect the stuck transactions.
+  #[inline]
+  pub fn max_active_read_version(&self) -> u64 {
+    self.inner.max_read_ts()
+  }
+
+  /// Returns the minimum read version of the transactions which are not done yet,
+  /// which is the frontier of the versions that can be discarded.
+  #[inline]
+  pub fn min_active_read_version(&self) -> Option<u64> {
+    self.inner.min_read_ts()
+  }
+
+  /// Sets a callback invoked with the gap between the commit version and the minimum active
+  /// read version on every commit, once the gap exceeds `max_gap`.
+  ///
+  /// A large gap means some transactions hold an old read version for long, so the database
+  /// has to keep many old versions, e.g. a leaked transaction.
+  #[inline]
+  pub fn set_version_gap_warning_threshold(
+    &self,
+    max_gap: u64,
+    warn_fn: impl Fn(u64) + Send + Sync + 'static,
+  ) {
+    self
+      .inner
+      .set_version_gap_warning(max_gap, Arc::new(warn_fn));
+  }
+
+  /// Returns the number of write transactions which are created but not discarded yet.
+  #[inline]
+  pub fn active_transaction_count(&self) -> u64 {
+    self.inner.active_txns()
+  }
+
+  /// Returns a snapshot of the aggregate counters of this transaction manager.
+  #[inline]
+  pub fn statistics(&self) -> TransactionStats {
+    self.inner.stats.snapshot()
+  }
+
   /// Create a new writable transaction.
   pub async fn read(&self) -> AsyncRtm<K, V, C, P, S> {
     AsyncRtm {
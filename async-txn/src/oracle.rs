@@ -1,11 +1,24 @@
-use core::ops::AddAssign;
-use std::borrow::Cow;
-
-use futures::lock::{Mutex, MutexGuard};
+use core::{
+  future::Future,
+  ops::AddAssign,
+  sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+use std::{
+  borrow::Cow,
+  collections::{btree_map::Entry, BTreeMap},
+  sync::{Arc, Mutex as StdMutex},
+};
+
+use futures::{
+  future::Either,
+  lock::{Mutex, MutexGuard},
+};
 use smallvec_wrapper::TinyVec;
-use txn_core::future::AsyncCm;
+use txn_core::{error::OracleError, future::AsyncCm};
+
+use wmark::{AsyncCloser, AsyncSpawner, AsyncWaterMark, WaterMarkError};
 
-use wmark::{AsyncCloser, AsyncSpawner, AsyncWaterMark};
+use super::TransactionStats;
 
 #[derive(Debug)]
 pub(super) struct OracleInner<C> {
@@ -21,6 +34,66 @@ pub(super) struct OracleInner<C> {
 pub(super) enum CreateCommitTimestampResult<C> {
   Timestamp(u64),
   Conflict(Option<C>),
+  InvalidVersion(Option<C>),
+}
+
+pub(super) enum CreateCommitTimestampsError {
+  Conflict,
+  InvalidVersion,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct Stats {
+  commits: AtomicU64,
+  conflicts: AtomicU64,
+  discards: AtomicU64,
+  reads: AtomicU64,
+}
+
+impl Stats {
+  #[inline]
+  pub(super) fn record_commit(&self) {
+    self.commits.fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[inline]
+  pub(super) fn record_conflict(&self) {
+    self.conflicts.fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[inline]
+  pub(super) fn record_discard(&self) {
+    self.discards.fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[inline]
+  pub(super) fn record_read(&self) {
+    self.reads.fetch_add(1, Ordering::Relaxed);
+  }
+
+  #[inline]
+  pub(super) fn snapshot(&self) -> TransactionStats {
+    TransactionStats {
+      total_commits: self.commits.load(Ordering::Relaxed),
+      total_conflicts: self.conflicts.load(Ordering::Relaxed),
+      total_discards: self.discards.load(Ordering::Relaxed),
+      total_reads: self.reads.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[derive(Clone)]
+struct VersionGapWarning {
+  max_gap: u64,
+  f: Arc<dyn Fn(u64) + Send + Sync>,
+}
+
+impl core::fmt::Debug for VersionGapWarning {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    f.debug_struct("VersionGapWarning")
+      .field("max_gap", &self.max_gap)
+      .finish_non_exhaustive()
+  }
 }
 
 #[derive(Debug)]
@@ -40,6 +113,26 @@ where
   /// Used to block new transaction, so all previous commits are visible to a new read.
   pub(super) txn_mark: AsyncWaterMark<S>,
 
+  /// The number of write transactions which are not discarded yet.
+  active_txns: AtomicU64,
+
+  /// Whether the oracle is closed, no write transaction can be created once closed.
+  closed: AtomicBool,
+
+  /// Notified when the last active write transaction is discarded after the oracle is closed.
+  drained_tx: async_channel::Sender<()>,
+  drained_rx: async_channel::Receiver<()>,
+
+  /// The aggregate counters of the transactions.
+  pub(super) stats: Stats,
+
+  /// The read timestamps which are not done yet, and the number of readers of each.
+  active_reads: StdMutex<BTreeMap<u64, usize>>,
+
+  /// The maximum gap between a commit timestamp and the minimum active read timestamp,
+  /// and the callback invoked with the gap once it is exceeded.
+  version_gap_warning: StdMutex<Option<VersionGapWarning>>,
+
   /// closer is used to stop watermarks.
   closer: AsyncCloser<S>,
 }
@@ -49,14 +142,32 @@ where
   C: AsyncCm,
   S: AsyncSpawner,
 {
+  /// Creates the commit timestamp of the transaction if it does not conflict.
+  ///
+  /// If `commit_ts` is given, it is used as the commit timestamp instead of the next one,
+  /// it must not be less than the next one.
   pub(super) async fn new_commit_ts(
     &self,
     done_read: &mut bool,
     read_ts: u64,
     mut conflict_manager: Option<C>,
+    commit_ts: Option<u64>,
   ) -> CreateCommitTimestampResult<C> {
     let mut inner = self.inner.lock().await;
 
+    if let Some(commit_ts) = commit_ts {
+      if commit_ts < inner.next_txn_ts {
+        return CreateCommitTimestampResult::InvalidVersion(conflict_manager);
+      }
+    }
+
+    // This is the general case, when user doesn't specify the read and commit ts.
+    let ts = commit_ts.unwrap_or(inner.next_txn_ts);
+    let next_txn_ts = match ts.checked_add(1) {
+      Some(next_txn_ts) => next_txn_ts,
+      None => return CreateCommitTimestampResult::InvalidVersion(conflict_manager),
+    };
+
     let conflict_manager = conflict_manager.take().unwrap();
 
     for committed_txn in inner.committed_txns.iter() {
@@ -77,20 +188,17 @@ where
       }
     }
 
-    let ts = {
+    {
       if !*done_read {
-        self.read_mark.done(read_ts).unwrap();
+        self.done_read(read_ts);
         *done_read = true;
       }
 
       self.cleanup_committed_transactions(true, &mut inner);
 
-      // This is the general case, when user doesn't specify the read and commit ts.
-      let ts = inner.next_txn_ts;
-      inner.next_txn_ts += 1;
+      inner.next_txn_ts = next_txn_ts;
       self.txn_mark.begin(ts).unwrap();
-      ts
-    };
+    }
 
     assert!(ts >= inner.last_cleanup_ts);
 
@@ -100,10 +208,78 @@ where
       ts,
       conflict_manager: Some(conflict_manager),
     });
+    drop(inner);
 
+    self.check_version_gap(ts);
     CreateCommitTimestampResult::Timestamp(ts)
   }
 
+  /// Creates the commit timestamps for a batch of transactions atomically, the transactions
+  /// are checked in order, so a transaction also conflicts with the previous ones in the batch.
+  ///
+  /// Returns a conflict if any transaction conflicts, or an invalid version if the timestamps
+  /// would overflow, in which case no timestamp is created, and the conflict managers are left
+  /// in `txns`.
+  pub(super) async fn new_commit_ts_batch(
+    &self,
+    txns: &mut [(&mut bool, u64, Option<C>)],
+  ) -> Result<Vec<u64>, CreateCommitTimestampsError> {
+    let mut inner = self.inner.lock().await;
+    if inner.next_txn_ts.checked_add(txns.len() as u64).is_none() {
+      return Err(CreateCommitTimestampsError::InvalidVersion);
+    }
+
+    for (idx, (_, read_ts, conflict_manager)) in txns.iter().enumerate() {
+      let conflict_manager = conflict_manager.as_ref().unwrap();
+      for committed_txn in inner.committed_txns.iter() {
+        if committed_txn.ts <= *read_ts {
+          continue;
+        }
+
+        if let Some(old_conflict_manager) = &committed_txn.conflict_manager {
+          if conflict_manager.has_conflict(old_conflict_manager).await {
+            return Err(CreateCommitTimestampsError::Conflict);
+          }
+        }
+      }
+
+      // All the previous transactions in the batch will be committed after this one started.
+      for (_, _, prev) in txns[..idx].iter() {
+        if conflict_manager.has_conflict(prev.as_ref().unwrap()).await {
+          return Err(CreateCommitTimestampsError::Conflict);
+        }
+      }
+    }
+
+    let mut timestamps = Vec::with_capacity(txns.len());
+    for (done_read, read_ts, conflict_manager) in txns.iter_mut() {
+      if !**done_read {
+        self.done_read(*read_ts);
+        **done_read = true;
+      }
+
+      self.cleanup_committed_transactions(true, &mut inner);
+
+      let ts = inner.next_txn_ts;
+      inner.next_txn_ts += 1;
+      self.txn_mark.begin(ts).unwrap();
+
+      assert!(ts >= inner.last_cleanup_ts);
+
+      inner.committed_txns.push(CommittedTxn {
+        ts,
+        conflict_manager: conflict_manager.take(),
+      });
+      timestamps.push(ts);
+    }
+    drop(inner);
+
+    if let Some(ts) = timestamps.last() {
+      self.check_version_gap(*ts);
+    }
+    Ok(timestamps)
+  }
+
   #[inline]
   fn cleanup_committed_transactions(
     &self,
@@ -143,6 +319,7 @@ where
     next_txn_ts: u64,
   ) -> Self {
     let closer = AsyncCloser::new(2);
+    let (drained_tx, drained_rx) = async_channel::bounded(1);
     let mut orc = Self {
       write_serialize_lock: Mutex::new(()),
       inner: Mutex::new(OracleInner {
@@ -152,6 +329,13 @@ where
       }),
       read_mark: AsyncWaterMark::new(read_mark_name),
       txn_mark: AsyncWaterMark::new(txn_mark_name),
+      active_txns: AtomicU64::new(0),
+      closed: AtomicBool::new(false),
+      drained_tx,
+      drained_rx,
+      stats: Stats::default(),
+      active_reads: StdMutex::new(BTreeMap::new()),
+      version_gap_warning: StdMutex::new(None),
       closer,
     };
 
@@ -166,7 +350,7 @@ where
       let inner = self.inner.lock().await;
 
       let read_ts = inner.next_txn_ts - 1;
-      self.read_mark.begin(read_ts).unwrap();
+      self.begin_read(read_ts);
       read_ts
     };
 
@@ -181,6 +365,74 @@ where
     read_ts
   }
 
+  /// Begins a read at the given timestamp, which must not be greater than the latest
+  /// committed one, and waits until all the commits up to it are done.
+  ///
+  /// The read is ended again if the wait fails.
+  pub(super) async fn begin_read_at(&self, read_ts: u64) -> Result<(), WaterMarkError> {
+    self.begin_read(read_ts);
+    if let Err(e) = self.txn_mark.wait_for_mark(read_ts).await {
+      self.done_read(read_ts);
+      return Err(e);
+    }
+    Ok(())
+  }
+
+  /// Advances the timestamp to `max(current, new_ts)`, so that the following reads
+  /// see `new_ts` as their read version and the following commits get a larger timestamp.
+  pub(super) async fn force_advance(&self, new_ts: u64) -> Result<(), OracleError> {
+    let next_ts = new_ts.checked_add(1).ok_or(OracleError::Overflow)?;
+    let mut inner = self.inner.lock().await;
+    if next_ts <= inner.next_txn_ts {
+      return Ok(());
+    }
+
+    inner.next_txn_ts = next_ts;
+    // No transaction will ever be committed at the skipped timestamps,
+    // mark new_ts as done, so the read transactions at new_ts will not be blocked.
+    self.txn_mark.done(new_ts).unwrap();
+    Ok(())
+  }
+
+  pub(super) async fn set_initial_version(&self, version: u64) -> Result<(), OracleError> {
+    let next_ts = version.checked_add(1).ok_or(OracleError::Overflow)?;
+    let mut inner = self.inner.lock().await;
+    if self.active_txns() > 0 || !self.active_reads.lock().unwrap().is_empty() {
+      return Err(OracleError::ActiveTransactions);
+    }
+
+    if next_ts <= inner.next_txn_ts {
+      return Err(OracleError::InvalidVersion);
+    }
+
+    inner.next_txn_ts = next_ts;
+    self.txn_mark.done(version).unwrap();
+    Ok(())
+  }
+
+  /// Allocates a commit timestamp without conflict detection, the caller must call
+  /// `done_commit` with the returned timestamp.
+  #[inline]
+  pub(super) async fn new_commit_ts_unchecked(&self) -> Result<u64, OracleError> {
+    let mut inner = self.inner.lock().await;
+    let ts = inner.next_txn_ts;
+    inner.next_txn_ts = ts.checked_add(1).ok_or(OracleError::Overflow)?;
+    self.txn_mark.begin(ts).unwrap();
+    Ok(ts)
+  }
+
+  /// Returns the timestamp the next commit would get, without allocating it.
+  #[inline]
+  pub(super) async fn peek_commit_ts(&self) -> u64 {
+    let _write_lock = self.write_serialize_lock.lock().await;
+    self.inner.lock().await.next_txn_ts
+  }
+
+  #[inline]
+  pub(super) async fn max_committed_ts(&self) -> u64 {
+    self.inner.lock().await.next_txn_ts - 1
+  }
+
   #[inline]
   pub(super) async fn increment_next_ts(&self) {
     self.inner.lock().await.next_txn_ts.add_assign(1);
@@ -191,10 +443,81 @@ where
     self.read_mark.done_until().unwrap()
   }
 
+  #[inline]
+  pub(super) fn begin_read(&self, read_ts: u64) {
+    *self
+      .active_reads
+      .lock()
+      .unwrap()
+      .entry(read_ts)
+      .or_default() += 1;
+    self.read_mark.begin(read_ts).unwrap();
+  }
+
   #[inline]
   pub(super) fn done_read(&self, read_ts: u64) {
+    {
+      let mut active_reads = self.active_reads.lock().unwrap();
+      if let Entry::Occupied(mut ent) = active_reads.entry(read_ts) {
+        *ent.get_mut() -= 1;
+        if *ent.get() == 0 {
+          ent.remove();
+        }
+      }
+    }
     self.read_mark.done(read_ts).unwrap();
   }
+
+  /// Returns the maximum read timestamp which is not done yet, or 0 if there is none.
+  #[inline]
+  pub(super) fn max_read_ts(&self) -> u64 {
+    let active_reads = self.active_reads.lock().unwrap();
+    active_reads.keys().next_back().copied().unwrap_or(0)
+  }
+
+  /// Returns the minimum read timestamp which is not done yet.
+  #[inline]
+  pub(super) fn min_read_ts(&self) -> Option<u64> {
+    let active_reads = self.active_reads.lock().unwrap();
+    active_reads.keys().next().copied()
+  }
+
+  #[inline]
+  pub(super) fn set_version_gap_warning(&self, max_gap: u64, f: Arc<dyn Fn(u64) + Send + Sync>) {
+    *self.version_gap_warning.lock().unwrap() = Some(VersionGapWarning { max_gap, f });
+  }
+
+  /// Invokes the version gap warning callback if the gap between `commit_ts` and the minimum
+  /// active read timestamp exceeds the threshold.
+  fn check_version_gap(&self, commit_ts: u64) {
+    let warning = self.version_gap_warning.lock().unwrap().clone();
+    if let (Some(warning), Some(min_read_ts)) = (warning, self.min_read_ts()) {
+      let gap = commit_ts.saturating_sub(min_read_ts);
+      if gap > warning.max_gap {
+        (warning.f)(gap);
+      }
+    }
+  }
+
+  /// Counts a new write transaction as active, returns `false` if the oracle is closed,
+  /// in which case the transaction must be discarded.
+  #[inline]
+  pub(super) fn begin_txn(&self) -> bool {
+    self.active_txns.fetch_add(1, Ordering::SeqCst);
+    !self.closed.load(Ordering::SeqCst)
+  }
+
+  #[inline]
+  pub(super) fn end_txn(&self) {
+    if self.active_txns.fetch_sub(1, Ordering::SeqCst) == 1 && self.closed.load(Ordering::SeqCst) {
+      let _ = self.drained_tx.try_send(());
+    }
+  }
+
+  #[inline]
+  pub(super) fn active_txns(&self) -> u64 {
+    self.active_txns.load(Ordering::Acquire)
+  }
 }
 
 impl<C, S> Oracle<C, S>
@@ -213,8 +536,26 @@ where
 {
   #[inline]
   pub(super) async fn stop(&self) {
+    self.closed.store(true, Ordering::SeqCst);
     self.closer.signal_and_wait().await;
   }
+
+  /// Closes the oracle, then waits until all the active write transactions are discarded
+  /// or the `grace` future completes.
+  pub(super) async fn drain(&self, grace: impl Future<Output = ()>) -> Result<(), OracleError> {
+    self.closed.store(true, Ordering::SeqCst);
+
+    let drained = async {
+      while self.active_txns.load(Ordering::SeqCst) > 0 {
+        // the sender is owned by the oracle, so the channel is never closed here.
+        let _ = self.drained_rx.recv().await;
+      }
+    };
+    match futures::future::select(core::pin::pin!(drained), core::pin::pin!(grace)).await {
+      Either::Left(_) => Ok(()),
+      Either::Right(_) => Err(OracleError::Timeout),
+    }
+  }
 }
 
 impl<C, S> Drop for Oracle<C, S>
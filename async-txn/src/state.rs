@@ -0,0 +1,22 @@
+/// The state of a write transaction, which can be shipped to another transaction manager
+/// to reconstruct the transaction, e.g. from the leader to a follower of a replicated database.
+///
+/// The reads and the conflict keys are kept as the fingerprints of the keys, so the
+/// transaction managers on both sides must fingerprint the keys with the same hasher.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransactionState<K, V> {
+  /// The read version of the transaction.
+  pub read_version: u64,
+  /// The fingerprints of the keys read by the transaction.
+  pub reads: Vec<u64>,
+  /// Whether the transaction has iterated the database.
+  pub iterated: bool,
+  /// The fingerprints of the keys written by the transaction.
+  pub conflict_keys: Vec<u64>,
+  /// The pending writes of the transaction as `(version, key, value)`, a `None` value
+  /// is a remove.
+  pub pending_writes: Vec<(u64, K, Option<V>)>,
+  /// The duplicate writes of the transaction, in the same form as the pending writes.
+  pub duplicate_writes: Vec<(u64, K, Option<V>)>,
+}
@@ -1,6 +1,8 @@
 use self::error::WtmError;
 
-use core::{borrow::Borrow, future::Future, hash::Hash};
+use core::{borrow::Borrow, future::Future, hash::Hash, ops::RangeBounds};
+
+use futures::future::Either;
 
 use super::*;
 
@@ -25,6 +27,27 @@ where
 
   pub(super) discarded: bool,
   pub(super) done_read: bool,
+
+  // cleanups of the external resources linked to this txn, invoked with
+  // `true` on a successful commit, or with `false` when the txn is discarded.
+  pub(super) cleanups: Vec<Box<dyn FnOnce(bool) + Send>>,
+  // hooks invoked with the commit timestamp once the writes are applied.
+  pub(super) commit_hooks: Vec<Box<dyn FnOnce(u64) + Send>>,
+  // whether the transaction is committed, a committed transaction is not counted as discarded.
+  pub(super) committed: bool,
+  // the maximum number of distinct keys the transaction can write.
+  pub(super) conflict_keys_limit: Option<usize>,
+  // whether the `get` and `contains_key` methods see the pending writes of the transaction.
+  pub(super) read_own_writes: bool,
+  // the number of the writes which overwrote a pending write of the same key, it is a history
+  // of the writes, only reset when the pending writes are drained, not when a key is retracted.
+  pub(super) overwrites: u64,
+  // the maximum number of the conflicts `try_commit` can retry, and the conflicts retried.
+  pub(super) retry_budget: Option<u32>,
+  pub(super) retry_count: u32,
+  // the entries threshold and the callback invoked when the remaining entries drop below it.
+  #[cfg(feature = "capacity-warn")]
+  pub(super) capacity_warn: Option<(u64, Arc<dyn Fn(u64, u64) + Send + Sync>)>,
 }
 
 impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
@@ -37,6 +60,46 @@ where
     self.read_ts
   }
 
+  /// Sets whether the `get` and `contains_key` methods see the pending writes of the
+  /// transaction, `true` by default.
+  ///
+  /// If `false`, the pending writes are invisible to them until the transaction is committed,
+  /// the keys are always read from the database and marked as read. The iterators still
+  /// merge the pending writes.
+  #[inline]
+  pub fn set_read_own_writes(&mut self, read_own_writes: bool) {
+    self.read_own_writes = read_own_writes;
+  }
+
+  /// Returns whether the `get` and `contains_key` methods see the pending writes of the
+  /// transaction.
+  #[inline]
+  pub const fn read_own_writes(&self) -> bool {
+    self.read_own_writes
+  }
+
+  /// Sets the maximum number of the conflicts [`try_commit`](AsyncWtm::try_commit) can retry.
+  ///
+  /// Once the budget is used up, a conflict discards the transaction and returns
+  /// [`TransactionError::RetriesExhausted`] instead of [`TransactionError::Conflict`].
+  #[inline]
+  pub fn set_retry_budget(&mut self, max_retries: u32) {
+    self.retry_budget = Some(max_retries);
+  }
+
+  /// Returns the number of the conflicts retried by [`try_commit`](AsyncWtm::try_commit).
+  #[inline]
+  pub const fn retry_count(&self) -> u32 {
+    self.retry_count
+  }
+
+  /// Returns the number of the older versions of the keys written in managed mode,
+  /// which will be committed along with the pending writes.
+  #[inline]
+  pub fn duplicate_write_count(&self) -> usize {
+    self.duplicate_writes.len()
+  }
+
   /// Sets the current read version of the transaction manager.
   // This should be used only for testing purposes.
   #[doc(hidden)]
@@ -60,6 +123,55 @@ where
   pub fn cm(&self) -> Option<&C> {
     self.conflict_manager.as_ref()
   }
+
+  /// Links an external resource (e.g. a file handle or a lock) to this transaction.
+  ///
+  /// All linked cleanups are invoked in registration order, with `true` when the transaction
+  /// is committed successfully, or with `false` when the transaction is discarded without
+  /// being committed (including a failed commit and dropping the transaction).
+  #[inline]
+  pub fn link_external_resource<F>(&mut self, cleanup: F)
+  where
+    F: FnOnce(bool) + Send + 'static,
+  {
+    self.cleanups.push(Box::new(cleanup));
+  }
+
+  /// Registers a hook which will be invoked with the commit timestamp when the transaction
+  /// is committed successfully.
+  ///
+  /// Hooks run in registration order, after the writes are applied to the database and
+  /// before the commit timestamp is marked as done, so no new read transaction can observe
+  /// the writes before the hooks finish. If the commit fails, or the transaction has nothing
+  /// to commit, no hook will be invoked.
+  #[inline]
+  pub fn on_commit<F>(&mut self, f: F)
+  where
+    F: FnOnce(u64) + Send + 'static,
+  {
+    self.commit_hooks.push(Box::new(f));
+  }
+
+  #[inline]
+  pub(super) fn mark_committed(&mut self) {
+    self.committed = true;
+    self.orc.stats.record_commit();
+  }
+
+  #[inline]
+  pub(super) fn run_cleanups(&mut self, committed: bool) {
+    run_cleanups(mem::take(&mut self.cleanups), committed);
+  }
+}
+
+#[inline]
+fn run_cleanups(cleanups: Vec<Box<dyn FnOnce(bool) + Send>>, committed: bool) {
+  cleanups.into_iter().for_each(|f| f(committed));
+}
+
+#[inline]
+pub(super) fn run_commit_hooks(hooks: Vec<Box<dyn FnOnce(u64) + Send>>, commit_ts: u64) {
+  hooks.into_iter().for_each(|f| f(commit_ts));
 }
 
 impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
@@ -112,6 +224,33 @@ where
       conflict_manager.mark_conflict(k).await;
     }
   }
+
+  /// Sets a callback invoked with the [`remaining_entries`](AsyncWtm::remaining_entries) and
+  /// the [`remaining_size`](AsyncWtm::remaining_size) after each successful write, once the
+  /// remaining entries drop below `entries_threshold`.
+  ///
+  /// The callback can be used to log a warning or to flush the transaction before a write
+  /// fails with [`TransactionError::LargeTxn`].
+  #[cfg(feature = "capacity-warn")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "capacity-warn")))]
+  #[inline]
+  pub fn set_capacity_warn_threshold<F>(&mut self, entries_threshold: u64, f: F)
+  where
+    F: Fn(u64, u64) + Send + Sync + 'static,
+  {
+    self.capacity_warn = Some((entries_threshold, Arc::new(f)));
+  }
+
+  #[cfg(feature = "capacity-warn")]
+  fn warn_capacity(&self, max_batch_entries: u64, max_batch_size: u64, version_overhead: u64) {
+    if let Some((threshold, f)) = &self.capacity_warn {
+      let remaining_entries = max_batch_entries.saturating_sub(self.count + 1);
+      if remaining_entries < *threshold {
+        let remaining_size = max_batch_size.saturating_sub(self.size + 1 + version_overhead);
+        f(remaining_entries, remaining_size);
+      }
+    }
+  }
 }
 
 impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
@@ -120,6 +259,122 @@ where
   P: AsyncPwm<Key = K, Value = V>,
   S: AsyncSpawner,
 {
+  /// Sets the maximum number of distinct keys the transaction can write, as every written key
+  /// is kept as a conflict key until the transaction is done.
+  ///
+  /// Writing a new key beyond the limit returns [`TransactionError::TooManyConflictKeys`].
+  /// The keys marked by [`mark_conflict`](AsyncWtm::mark_conflict) directly are not limited.
+  #[inline]
+  pub fn set_conflict_keys_limit(&mut self, limit: usize) {
+    self.conflict_keys_limit = Some(limit);
+  }
+
+  /// Returns the maximum number of entries of the transaction, 0 if it has been committed.
+  #[inline]
+  pub fn max_batch_entries(&self) -> u64 {
+    self
+      .pending_writes
+      .as_ref()
+      .map_or(0, |pending_writes| pending_writes.max_batch_entries())
+  }
+
+  /// Returns the maximum size of the transaction, 0 if it has been committed.
+  #[inline]
+  pub fn max_batch_size(&self) -> u64 {
+    self
+      .pending_writes
+      .as_ref()
+      .map_or(0, |pending_writes| pending_writes.max_batch_size())
+  }
+
+  /// Returns the number of entries can still be written before the transaction
+  /// becomes too large.
+  #[inline]
+  pub fn remaining_entries(&self) -> u64 {
+    self.max_batch_entries().saturating_sub(self.count + 1)
+  }
+
+  /// Checks that no write of the transaction overwrote a pending write of the same key, e.g.
+  /// to catch the duplicates of a data source in a batch import.
+  ///
+  /// Returns [`TransactionError::DuplicateKey`] if any key is written more than once since
+  /// the transaction was created or its pending writes were drained, even if the key is
+  /// retracted afterwards, e.g. by [`revert_key`](AsyncWtm::revert_key).
+  #[inline]
+  pub fn guarantee_unique_keys(&self) -> Result<(), TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    if self.overwrites > 0 {
+      return Err(TransactionError::DuplicateKey);
+    }
+    Ok(())
+  }
+
+  /// Returns the size left of the transaction, an entry can still be written if its estimated
+  /// size is not larger than this.
+  #[inline]
+  pub fn remaining_size(&self) -> u64 {
+    self
+      .max_batch_size()
+      .saturating_sub(self.size + 1 + P::VERSION_OVERHEAD)
+  }
+
+  /// Returns the estimated size of the entries to be committed, including the
+  /// [`VERSION_OVERHEAD`](AsyncPwm::VERSION_OVERHEAD) of each entry.
+  #[inline]
+  pub const fn estimate_total_commit_size(&self) -> u64 {
+    self.size
+  }
+
+  /// Returns the timestamp the transaction would get if it was committed now, without
+  /// committing it.
+  ///
+  /// The returned timestamp is advisory only, the actual commit timestamp may be different
+  /// because other transactions may commit in between.
+  pub async fn peek_commit_ts(&self) -> Result<u64, TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    Ok(self.orc.peek_commit_ts().await)
+  }
+
+  /// Returns the number of keys with a pending insert, a key inserted many times is counted once.
+  pub async fn pending_inserts_count(&self) -> usize {
+    match &self.pending_writes {
+      Some(pending_writes) => pending_writes
+        .iter()
+        .await
+        .filter(|(_, ent)| ent.value.is_some())
+        .count(),
+      None => 0,
+    }
+  }
+
+  /// Returns the number of entries will be committed, including the
+  /// [duplicate writes](AsyncWtm::duplicate_write_count).
+  pub async fn total_entry_count(&self) -> usize {
+    let pending = match &self.pending_writes {
+      Some(pending_writes) => pending_writes.len().await,
+      None => 0,
+    };
+    pending + self.duplicate_write_count()
+  }
+
+  /// Returns the number of keys with a pending remove.
+  pub async fn pending_removes_count(&self) -> usize {
+    match &self.pending_writes {
+      Some(pending_writes) => pending_writes
+        .iter()
+        .await
+        .filter(|(_, ent)| ent.value.is_none())
+        .count(),
+      None => 0,
+    }
+  }
+
   /// Rolls back the transaction.
   #[inline]
   pub async fn rollback(&mut self) -> Result<(), TransactionError<C::Error, P::Error>> {
@@ -144,6 +399,150 @@ where
     Ok(())
   }
 
+  /// Waits until all the transactions with commit timestamp less than or equal to `target_ts`
+  /// are visible, or the `timeout` future completes.
+  ///
+  /// The transaction manager is runtime agnostic, so the timer should be provided by the caller,
+  /// e.g. `tokio::time::sleep(Duration::from_secs(1))`.
+  ///
+  /// Returns [`TransactionError::Timeout`] if the `timeout` future completes first, and
+  /// [`TransactionError::Canceled`] if the watermark fails to wait for the version.
+  pub async fn wait_for_version(
+    &self,
+    target_ts: u64,
+    timeout: impl Future<Output = ()>,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    let wait = self.orc.txn_mark.wait_for_mark(target_ts);
+    match futures::future::select(core::pin::pin!(wait), core::pin::pin!(timeout)).await {
+      Either::Left((rst, _)) => rst.map_err(|_| TransactionError::Canceled),
+      Either::Right(_) => Err(TransactionError::Timeout),
+    }
+  }
+
+  /// Checks the freshness of this transaction, returns [`TransactionError::StaleTransaction`]
+  /// if the read version lags more than `max_lag` versions behind the latest committed version.
+  ///
+  /// This is useful to be called right before `commit*` when the SLA requires fresh reads.
+  pub async fn apply_if_fresh(
+    &self,
+    max_lag: u64,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let lag = self
+      .orc
+      .max_committed_ts()
+      .await
+      .saturating_sub(self.read_ts);
+    if lag > max_lag {
+      return Err(TransactionError::StaleTransaction { lag });
+    }
+    Ok(())
+  }
+
+  /// Retracts the pending write of the key, without adding a delete marker for it.
+  ///
+  /// Returns `true` if the key was in the pending writes, `false` otherwise. The duplicate
+  /// writes of the key are retracted as well.
+  ///
+  /// The key is still marked as a conflict key, as the conflict manager
+  /// does not support unmarking.
+  pub async fn revert_key(&mut self, key: &K) -> Result<bool, TransactionError<C::Error, P::Error>>
+  where
+    K: Eq,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    match pending_writes
+      .remove_entry(key)
+      .await
+      .map_err(TransactionError::Pwm)?
+    {
+      Some((k, v)) => {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+
+        let size = &mut self.size;
+        let count = &mut self.count;
+        self.duplicate_writes.retain(|ent| {
+          if ent.key() != key {
+            return true;
+          }
+          *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+          *count = count.saturating_sub(1);
+          false
+        });
+        Ok(true)
+      }
+      None => Ok(false),
+    }
+  }
+
+  /// Applies `f` to the value of every pending insert in place, pending removes are left untouched.
+  ///
+  /// The size and count of the transaction are recomputed afterwards, the limits are not
+  /// checked against them, so a later write may fail with [`TransactionError::LargeTxn`].
+  pub async fn map_values<F>(
+    &mut self,
+    mut f: F,
+  ) -> Result<(), TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+    F: FnMut(&K, V) -> V,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .await
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    let mut size = 0;
+    for key in keys {
+      if let Some((k, mut v)) = pending_writes
+        .remove_entry(&key)
+        .await
+        .map_err(TransactionError::Pwm)?
+      {
+        v.value = v.value.map(|value| f(&k, value));
+        let ent = Entry::unsplit(k, v);
+        size += pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
+        let (k, v) = ent.split();
+        pending_writes
+          .insert(k, v)
+          .await
+          .map_err(TransactionError::Pwm)?;
+      }
+    }
+
+    self.duplicate_writes = mem::take(&mut self.duplicate_writes)
+      .into_iter()
+      .map(|ent| {
+        let (k, mut v) = ent.split();
+        v.value = v.value.map(|value| f(&k, value));
+        let ent = Entry::unsplit(k, v);
+        size += pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
+        ent
+      })
+      .collect();
+
+    self.count = (pending_writes.len().await + self.duplicate_writes.len()) as u64;
+    self.size = size;
+    Ok(())
+  }
+
   /// Insert a key-value pair to the transaction.
   pub async fn insert(
     &mut self,
@@ -153,6 +552,40 @@ where
     self.insert_with_in(key, value).await
   }
 
+  /// Inserts the pre-built entries to the transaction in order, e.g. the entries decoded from
+  /// a WAL, both the insert and the remove entries are accepted.
+  ///
+  /// The versions of the entries are overwritten with the read version of the transaction,
+  /// as the pending writes are versioned at the commit timestamp. If an entry fails, the
+  /// entries before it stay in the transaction and the error is returned.
+  pub async fn insert_many_raw(
+    &mut self,
+    entries: impl IntoIterator<Item = Entry<K, V>>,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    for mut ent in entries {
+      ent.version = self.read_ts;
+      self.modify(ent).await?;
+    }
+    Ok(())
+  }
+
+  /// Insert a key-value pair with a time-to-live hint to the transaction.
+  ///
+  /// The transaction manager only passes the `ttl` through to [`Entry::ttl`],
+  /// the database is responsible for enforcing it when applying the entries.
+  #[cfg(feature = "ttl")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "ttl")))]
+  pub async fn insert_with_ttl(
+    &mut self,
+    key: K,
+    value: V,
+    ttl: core::time::Duration,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    let mut ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
+    ent.ttl = Some(ttl);
+    self.modify(ent).await
+  }
+
   /// Removes a key.
   ///
   /// This is done by adding a delete marker for the key at commit timestamp.  Any
@@ -160,10 +593,7 @@ where
   /// this commit would see the deletion.
   pub async fn remove(&mut self, key: K) -> Result<(), TransactionError<C::Error, P::Error>> {
     self
-      .modify(Entry {
-        data: EntryData::Remove(key),
-        version: 0,
-      })
+      .modify(Entry::new(self.read_ts, EntryData::Remove(key)))
       .await
   }
 
@@ -176,14 +606,18 @@ where
       return Err(TransactionError::Discard);
     }
 
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get(key)
-      .await
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get(key)
+        .await
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -207,6 +641,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub async fn get<'a, 'b: 'a>(
     &'a mut self,
     key: &'b K,
@@ -214,20 +651,20 @@ where
     if self.discarded {
       return Err(TransactionError::Discard);
     }
+    self.orc.stats.record_read();
 
-    if let Some(e) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get(key)
-      .await
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some(e) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -258,14 +695,71 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. If callback is provided, Badger will return immediately after checking
-  /// for conflicts. Writes to the database will happen in the background.  If
-  /// there is a conflict, an error will be returned and the callback will not
-  /// run. If there are no conflicts, the callback will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    for conflicts. Writes to the database will happen in the background.  If
+  ///    there is a conflict, an error will be returned and the callback will not
+  ///    run. If there are no conflicts, the callback will be called in the
+  ///    background upon successful completion of writes or any error during write.
   pub async fn commit<F, Fut, O, E>(
     &mut self,
     apply: F,
   ) -> Result<O, WtmError<C::Error, P::Error, E>>
+  where
+    Fut: Future<Output = Result<O, E>>,
+    F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
+    E: std::error::Error,
+  {
+    self.commit_in(None, false, apply).await
+  }
+
+  /// Acts like [`commit`](AsyncWtm::commit), but the transaction is not discarded if it
+  /// conflicts, so it can be retried with the same pending writes.
+  ///
+  /// On [`TransactionError::Conflict`], the read version, the reads and the pending writes of
+  /// the transaction are kept, so retrying as is conflicts again. The caller should call
+  /// [`reset_read_ts_to_latest`](AsyncWtm::reset_read_ts_to_latest), read the keys again and
+  /// update the pending writes before retrying.
+  pub async fn try_commit<F, Fut, O, E>(
+    &mut self,
+    apply: F,
+  ) -> Result<O, WtmError<C::Error, P::Error, E>>
+  where
+    Fut: Future<Output = Result<O, E>>,
+    F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
+    E: std::error::Error,
+  {
+    self.commit_in(None, true, apply).await
+  }
+
+  /// Acts like [`commit`](AsyncWtm::commit), but the transaction is committed at the given
+  /// version instead of the next one, e.g. a version from an external clock.
+  ///
+  /// The conflict detection works exactly as [`commit`](AsyncWtm::commit) does. Returns
+  /// [`TransactionError::InvalidVersion`] if the version is not greater than the read version
+  /// of the transaction or the latest committed version.
+  pub async fn commit_at_version<F, Fut, O, E>(
+    &mut self,
+    version: u64,
+    apply: F,
+  ) -> Result<O, WtmError<C::Error, P::Error, E>>
+  where
+    Fut: Future<Output = Result<O, E>>,
+    F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
+    E: std::error::Error,
+  {
+    if version <= self.read_ts {
+      self.discard();
+      return Err(WtmError::transaction(TransactionError::InvalidVersion));
+    }
+
+    self.commit_in(Some(version), false, apply).await
+  }
+
+  async fn commit_in<F, Fut, O, E>(
+    &mut self,
+    commit_ts: Option<u64>,
+    retry_on_conflict: bool,
+    apply: F,
+  ) -> Result<O, WtmError<C::Error, P::Error, E>>
   where
     Fut: Future<Output = Result<O, E>>,
     F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
@@ -273,14 +767,22 @@ where
   {
     if self.pending_writes.as_ref().unwrap().is_empty().await {
       // Nothing to commit
+      let rst = apply(Default::default()).await.map_err(WtmError::commit);
+      if rst.is_ok() {
+        self.mark_committed();
+      }
+      self.run_cleanups(rst.is_ok());
       self.discard();
-      return apply(Default::default()).await.map_err(WtmError::commit);
+      return rst;
     }
 
-    match self.commit_entries().await {
+    match self.commit_entries_at(commit_ts).await {
       Ok((commit_ts, entries)) => match apply(entries).await {
         Ok(output) => {
+          run_commit_hooks(mem::take(&mut self.commit_hooks), commit_ts);
           self.orc.done_commit(commit_ts);
+          self.mark_committed();
+          self.run_cleanups(true);
           self.discard();
           Ok(output)
         }
@@ -290,23 +792,260 @@ where
           Err(WtmError::commit(e))
         }
       },
+      Err(TransactionError::Conflict)
+        if self
+          .retry_budget
+          .is_some_and(|budget| self.retry_count >= budget) =>
+      {
+        self.discard();
+        Err(WtmError::transaction(TransactionError::RetriesExhausted(
+          self.retry_count,
+        )))
+      }
+      Err(TransactionError::Conflict) if retry_on_conflict => {
+        self.retry_count += 1;
+        Err(WtmError::transaction(TransactionError::Conflict))
+      }
       Err(e) => {
         self.discard();
         Err(WtmError::transaction(e))
       }
     }
   }
-}
 
-impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
-where
-  C: AsyncCmEquivalent<Key = K>,
-  P: AsyncPwm<Key = K, Value = V>,
-  S: AsyncSpawner,
-{
-  /// Marks a key is read.
-  pub async fn mark_read_equivalent<Q>(&mut self, k: &Q)
-  where
+  /// Advances the read version of the transaction to the latest committed version.
+  async fn refresh_read_ts(&mut self) {
+    let read_ts = self.orc.read_ts().await;
+    self.done_read();
+    self.read_ts = read_ts;
+    self.done_read = false;
+  }
+
+  /// Acts like [`commit`](AsyncWtm::commit), but `preview` is invoked with the final entries and
+  /// the commit timestamp after the conflict check and before `apply`, e.g. for WAL pre-logging.
+  ///
+  /// If there are no writes, `preview` is invoked with an empty slice and the read version.
+  pub async fn preview_commit_entries<PF, PFut, R, F, Fut, E>(
+    &mut self,
+    preview: PF,
+    apply: F,
+  ) -> Result<R, WtmError<C::Error, P::Error, E>>
+  where
+    PF: FnOnce(&[Entry<K, V>], u64) -> PFut,
+    PFut: Future<Output = R>,
+    Fut: Future<Output = Result<(), E>>,
+    F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
+    E: std::error::Error,
+  {
+    if self.discarded {
+      return Err(WtmError::transaction(TransactionError::Discard));
+    }
+
+    if self.pending_writes.as_ref().unwrap().is_empty().await {
+      // Nothing to commit
+      let output = preview(&[], self.read_ts).await;
+      let rst = apply(Default::default()).await.map_err(WtmError::commit);
+      if rst.is_ok() {
+        self.mark_committed();
+      }
+      self.run_cleanups(rst.is_ok());
+      self.discard();
+      return rst.map(|_| output);
+    }
+
+    match self.commit_entries().await {
+      Ok((commit_ts, entries)) => {
+        let output = preview(&entries, commit_ts).await;
+        match apply(entries).await {
+          Ok(_) => {
+            run_commit_hooks(mem::take(&mut self.commit_hooks), commit_ts);
+            self.orc.done_commit(commit_ts);
+            self.mark_committed();
+            self.run_cleanups(true);
+            self.discard();
+            Ok(output)
+          }
+          Err(e) => {
+            self.orc.done_commit(commit_ts);
+            self.discard();
+            Err(WtmError::commit(e))
+          }
+        }
+      }
+      Err(e) => {
+        self.discard();
+        Err(WtmError::transaction(e))
+      }
+    }
+  }
+
+  /// Acts like [`commit`](AsyncWtm::commit), but links the given `cleanup` to this transaction
+  /// before committing, see [`link_external_resource`](AsyncWtm::link_external_resource).
+  pub async fn commit_with_resource_cleanup<F, Fut, O, E>(
+    &mut self,
+    apply: F,
+    cleanup: impl FnOnce(bool) + Send + 'static,
+  ) -> Result<O, WtmError<C::Error, P::Error, E>>
+  where
+    Fut: Future<Output = Result<O, E>>,
+    F: FnOnce(OneOrMore<Entry<K, V>>) -> Fut,
+    E: std::error::Error,
+  {
+    self.link_external_resource(cleanup);
+    self.commit(apply).await
+  }
+}
+
+impl<K, V, H, P, S> AsyncWtm<K, V, HashCm<K, H>, P, S>
+where
+  K: Hash + Eq,
+  H: core::hash::BuildHasher,
+  P: AsyncPwm<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Re-pins the transaction at the latest committed version, e.g. after the transaction
+  /// becomes [`TransactionError::StaleTransaction`], the pending writes are kept.
+  ///
+  /// The read mark of the old read version is released, and the tracked reads are cleared as
+  /// they are read from the old snapshot, the keys must be read again to be tracked.
+  pub async fn reset_read_ts_to_latest(
+    &mut self,
+  ) -> Result<(), TransactionError<core::convert::Infallible, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    if let Some(cm) = self.conflict_manager.as_mut() {
+      cm.clear_reads();
+    }
+    self.refresh_read_ts().await;
+    Ok(())
+  }
+
+  /// Advances the read version of the transaction to `new_ts`, e.g. to see the newer
+  /// committed data before retrying a [`try_commit`](AsyncWtm::try_commit).
+  ///
+  /// As [`reset_read_ts_to_latest`](AsyncWtm::reset_read_ts_to_latest), the tracked reads are
+  /// cleared as they are read from the old snapshot, the keys must be read again to be
+  /// tracked. Otherwise a commit between the two versions to a key read before would not
+  /// be checked for conflicts.
+  ///
+  /// Returns [`TransactionError::InvalidVersion`] if `new_ts` is less than the current read
+  /// version or greater than the latest committed version, and [`TransactionError::Canceled`]
+  /// if the watermark fails to wait for `new_ts`, the read version and the reads are unchanged
+  /// then.
+  pub async fn set_read_ts(
+    &mut self,
+    new_ts: u64,
+  ) -> Result<(), TransactionError<core::convert::Infallible, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    if new_ts < self.read_ts || new_ts > self.orc.max_committed_ts().await {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    if new_ts != self.read_ts {
+      self
+        .orc
+        .begin_read_at(new_ts)
+        .await
+        .map_err(|_| TransactionError::Canceled)?;
+      if let Some(cm) = self.conflict_manager.as_mut() {
+        cm.clear_reads();
+      }
+      self.done_read();
+      self.read_ts = new_ts;
+      self.done_read = false;
+    }
+    Ok(())
+  }
+
+  /// Forgets all but the last `n_reads_ago` reads tracked by the transaction, e.g. for a
+  /// long-running rolling window processor which knows its older reads are safe to forget.
+  /// Returns the number of the forgotten reads.
+  ///
+  /// # Warning
+  /// The forgotten reads are no longer checked for conflicts, so a concurrent write to a
+  /// forgotten key does not make this transaction conflict, which breaks the serializable
+  /// isolation for those keys. The conflict keys of the writes are kept, so other
+  /// transactions still conflict with the writes of this transaction.
+  #[inline]
+  pub fn evict_conflict_keys_older_than(&mut self, n_reads_ago: usize) -> usize {
+    self
+      .conflict_manager
+      .as_mut()
+      .map_or(0, |cm| cm.retain_last_reads(n_reads_ago))
+  }
+}
+
+impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
+where
+  K: Clone,
+  V: Clone,
+  C: AsyncCm<Key = K> + Clone,
+  P: AsyncPwmSnapshot<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Forks this transaction, the new transaction has the same read version, the copy of the
+  /// pending writes and the tracked reads and writes of this transaction, which is useful for
+  /// speculative execution. The linked external resources and commit hooks are not forked.
+  pub async fn fork(&self) -> Result<Self, TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let snapshot = self
+      .pending_writes
+      .as_ref()
+      .unwrap()
+      .snapshot()
+      .await
+      .map_err(TransactionError::Pwm)?;
+    let pending_writes = P::from_snapshot(snapshot)
+      .await
+      .map_err(TransactionError::Pwm)?;
+
+    // The read version of this transaction is not done, so it is safe to mark it again.
+    self.orc.begin_read(self.read_ts);
+    let wtm = Self {
+      orc: self.orc.clone(),
+      read_ts: self.read_ts,
+      size: self.size,
+      count: self.count,
+      conflict_manager: self.conflict_manager.clone(),
+      pending_writes: Some(pending_writes),
+      duplicate_writes: self.duplicate_writes.clone(),
+      discarded: false,
+      done_read: false,
+      cleanups: Vec::new(),
+      commit_hooks: Vec::new(),
+      committed: false,
+      conflict_keys_limit: self.conflict_keys_limit,
+      read_own_writes: self.read_own_writes,
+      overwrites: self.overwrites,
+      retry_budget: self.retry_budget,
+      retry_count: self.retry_count,
+      #[cfg(feature = "capacity-warn")]
+      capacity_warn: self.capacity_warn.clone(),
+    };
+    if !self.orc.begin_txn() {
+      return Err(TransactionError::Closed);
+    }
+    Ok(wtm)
+  }
+}
+
+impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
+where
+  C: AsyncCmEquivalent<Key = K>,
+  P: AsyncPwm<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Marks a key is read.
+  pub async fn mark_read_equivalent<Q>(&mut self, k: &Q)
+  where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Hash,
   {
@@ -350,14 +1089,18 @@ where
       return Err(TransactionError::Discard);
     }
 
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_equivalent(key)
-      .await
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_equivalent(key)
+        .await
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -381,6 +1124,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub async fn get_equivalent<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -392,20 +1138,20 @@ where
     if self.discarded {
       return Err(TransactionError::Discard);
     }
+    self.orc.stats.record_read();
 
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_equivalent(key)
-      .await
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_equivalent(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -445,14 +1191,18 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_equivalent(key)
-      .await
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_equivalent(key)
+        .await
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -476,6 +1226,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub async fn get_comparable_cm_equivalent_pm<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -484,19 +1237,23 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_equivalent(key)
-      .await
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_equivalent(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -564,14 +1321,18 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Ord,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_comparable(key)
-      .await
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_comparable(key)
+        .await
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -595,6 +1356,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub async fn get_comparable<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -603,19 +1367,23 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Ord,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_comparable(key)
-      .await
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_comparable(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -655,14 +1423,18 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_comparable(key)
-      .await
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_comparable(key)
+        .await
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -686,6 +1458,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub async fn get_equivalent_cm_comparable_pm<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -694,19 +1469,23 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_comparable(key)
-      .await
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_comparable(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -745,9 +1524,9 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. Return immediately after checking for conflicts.
-  /// If there is a conflict, an error will be returned immediately and the no task will be spawned
-  /// run. If there are no conflicts, a task will be spawned and the future will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    If there is a conflict, an error will be returned immediately and the no task will be spawned
+  ///    run. If there are no conflicts, a task will be spawned and the future will be called in the
+  ///    background upon successful completion of writes or any error during write.
   pub async fn commit_with_task<F, Fut, CFut, E, R>(
     &mut self,
     apply: F,
@@ -769,21 +1548,32 @@ where
 
     if self.pending_writes.as_ref().unwrap().is_empty().await {
       // Nothing to commit
+      self.mark_committed();
+      self.run_cleanups(true);
       self.discard();
       return Ok(S::spawn(async move { fut(Ok(())).await }));
     }
 
     match self.commit_entries().await {
       Ok((commit_ts, entries)) => {
+        // the result of the commit is recorded by the spawned task.
+        self.committed = true;
         let orc = self.orc.clone();
+        let cleanups = mem::take(&mut self.cleanups);
+        let hooks = mem::take(&mut self.commit_hooks);
         Ok(S::spawn(async move {
           match apply(entries).await {
             Ok(_) => {
+              run_commit_hooks(hooks, commit_ts);
+              orc.stats.record_commit();
               orc.done_commit(commit_ts);
+              run_cleanups(cleanups, true);
               fut(Ok(())).await
             }
             Err(e) => {
+              orc.stats.record_discard();
               orc.done_commit(commit_ts);
+              run_cleanups(cleanups, false);
               fut(Err(e)).await
             }
           }
@@ -811,10 +1601,7 @@ where
     key: K,
     value: V,
   ) -> Result<(), TransactionError<C::Error, P::Error>> {
-    let ent = Entry {
-      data: EntryData::Insert { key, value },
-      version: self.read_ts,
-    };
+    let ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
 
     self.modify(ent).await
   }
@@ -832,11 +1619,23 @@ where
 
     let cnt = self.count + 1;
     // Extra bytes for the version in key.
-    let size = self.size + pending_writes.estimate_size(&ent);
+    let size = self.size + pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
     if cnt >= pending_writes.max_batch_entries() || size >= pending_writes.max_batch_size() {
       return Err(TransactionError::LargeTxn);
     }
 
+    if let Some(limit) = self.conflict_keys_limit {
+      if self.conflict_manager.is_some()
+        && pending_writes.len().await >= limit
+        && !pending_writes
+          .contains_key(ent.key())
+          .await
+          .map_err(TransactionError::Pwm)?
+      {
+        return Err(TransactionError::TooManyConflictKeys);
+      }
+    }
+
     self.count = cnt;
     self.size = size;
 
@@ -857,6 +1656,7 @@ where
       .await
       .map_err(TransactionError::Pwm)?
     {
+      self.overwrites += 1;
       if old_value.version != eversion {
         self
           .duplicate_writes
@@ -868,17 +1668,34 @@ where
       .await
       .map_err(TransactionError::Pwm)?;
 
+    #[cfg(feature = "capacity-warn")]
+    {
+      let (max_batch_entries, max_batch_size) = (
+        pending_writes.max_batch_entries(),
+        pending_writes.max_batch_size(),
+      );
+      self.warn_capacity(max_batch_entries, max_batch_size, P::VERSION_OVERHEAD);
+    }
+
     Ok(())
   }
 
   async fn commit_entries(
     &mut self,
+  ) -> Result<(u64, OneOrMore<Entry<K, V>>), TransactionError<C::Error, P::Error>> {
+    self.commit_entries_at(None).await
+  }
+
+  async fn commit_entries_at(
+    &mut self,
+    commit_ts: Option<u64>,
   ) -> Result<(u64, OneOrMore<Entry<K, V>>), TransactionError<C::Error, P::Error>> {
     // Ensure that the order in which we get the commit timestamp is the same as
     // the order in which we push these updates to the write channel. So, we
     // acquire a writeChLock before getting a commit timestamp, and only release
     // it after pushing the entries to it.
-    let _write_lock = self.orc.write_serialize_lock.lock().await;
+    let orc = self.orc.clone();
+    let _write_lock = orc.write_serialize_lock.lock().await;
 
     let conflict_manager = if self.conflict_manager.is_none() {
       None
@@ -888,52 +1705,180 @@ where
 
     match self
       .orc
-      .new_commit_ts(&mut self.done_read, self.read_ts, conflict_manager)
+      .new_commit_ts(
+        &mut self.done_read,
+        self.read_ts,
+        conflict_manager,
+        commit_ts,
+      )
       .await
     {
       CreateCommitTimestampResult::Conflict(conflict_manager) => {
         // If there is a conflict, we should not send the updates to the write channel.
         // Instead, we should return the conflict error to the user.
+        self.orc.stats.record_conflict();
         self.conflict_manager = conflict_manager;
         Err(TransactionError::Conflict)
       }
+      CreateCommitTimestampResult::InvalidVersion(conflict_manager) => {
+        self.conflict_manager = conflict_manager;
+        Err(TransactionError::InvalidVersion)
+      }
       CreateCommitTimestampResult::Timestamp(commit_ts) => {
-        let pending_writes = mem::take(&mut self.pending_writes).unwrap();
-        let duplicate_writes = mem::take(&mut self.duplicate_writes);
-        let mut entries =
-          OneOrMore::with_capacity(pending_writes.len().await + self.duplicate_writes.len());
-
-        let process_entry = |entries: &mut OneOrMore<Entry<K, V>>, mut ent: Entry<K, V>| {
-          ent.version = commit_ts;
-          entries.push(ent);
-        };
-        pending_writes
-          .into_iter()
-          .await
-          .for_each(|(k, v)| process_entry(&mut entries, Entry::unsplit(k, v)));
-        duplicate_writes
-          .into_iter()
-          .for_each(|ent| process_entry(&mut entries, ent));
-
-        // CommitTs should not be zero if we're inserting transaction markers.
-        assert_ne!(commit_ts, 0);
-
+        let entries = self.take_entries(commit_ts).await;
         Ok((commit_ts, entries))
       }
     }
   }
-}
-
-impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
-where
-  S: AsyncSpawner,
-{
-  fn done_read(&mut self) {
-    if !self.done_read {
-      self.done_read = true;
-      self.orc().read_mark.done(self.read_ts).unwrap();
-    }
-  }
+
+  /// Drains the pending writes and the duplicate writes of the transaction, e.g. to move them
+  /// into another transaction, the transaction stays live and can accept new writes.
+  ///
+  /// The versions of the entries are left as they are written. The drained keys are still
+  /// marked as conflict keys, as the conflict manager does not support unmarking.
+  pub async fn pending_entries_drain(
+    &mut self,
+  ) -> Result<OneOrMore<Entry<K, V>>, TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .await
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    let mut entries = mem::take(&mut self.duplicate_writes);
+    for key in keys {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(&key)
+        .await
+        .map_err(TransactionError::Pwm)?
+      {
+        entries.push(Entry::unsplit(k, v));
+      }
+    }
+    self.count = 0;
+    self.size = 0;
+    self.overwrites = 0;
+    Ok(entries)
+  }
+
+  /// Drains the pending removes of the transaction and returns their keys, e.g. to replicate
+  /// the removes separately from the inserts.
+  ///
+  /// The removes at older versions kept as duplicate writes are dropped as well, their keys
+  /// have a newer pending write, which is drained here if it is a remove too.
+  ///
+  /// The drained keys are still marked as conflict keys, as the conflict manager does not
+  /// support unmarking.
+  pub async fn drain_removes(&mut self) -> Result<Vec<K>, TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .await
+      .filter(|(_, v)| v.value.is_none())
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    for key in keys.iter() {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+      {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+      }
+    }
+
+    let size = &mut self.size;
+    let count = &mut self.count;
+    self.duplicate_writes.retain(|ent| {
+      if !matches!(ent.data, EntryData::Remove(_)) {
+        return true;
+      }
+      *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+      *count = count.saturating_sub(1);
+      false
+    });
+    Ok(keys)
+  }
+
+  /// Consumes the transaction and returns its write set without committing it, e.g. to ship
+  /// the writes to another replica, the transaction is discarded.
+  ///
+  /// The versions of the entries are left as they are written, the read version of the
+  /// transaction, as no commit version is assigned.
+  pub async fn into_entries(
+    mut self,
+  ) -> Result<OneOrMore<Entry<K, V>>, TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = mem::take(&mut self.pending_writes).unwrap();
+    let mut entries = mem::take(&mut self.duplicate_writes);
+    pending_writes
+      .into_iter()
+      .await
+      .for_each(|(k, v)| entries.push(Entry::unsplit(k, v)));
+    self.discard();
+    Ok(entries)
+  }
+
+  /// Takes the pending writes and the duplicate writes as the entries to be applied
+  /// at the given commit timestamp.
+  pub(super) async fn take_entries(&mut self, commit_ts: u64) -> OneOrMore<Entry<K, V>> {
+    let pending_writes = mem::take(&mut self.pending_writes).unwrap();
+    let duplicate_writes = mem::take(&mut self.duplicate_writes);
+    let mut entries = OneOrMore::with_capacity(pending_writes.len().await + duplicate_writes.len());
+
+    let process_entry = |entries: &mut OneOrMore<Entry<K, V>>, mut ent: Entry<K, V>| {
+      ent.version = commit_ts;
+      entries.push(ent);
+    };
+    pending_writes
+      .drain_to_vec()
+      .await
+      .into_iter()
+      .for_each(|(k, v)| process_entry(&mut entries, Entry::unsplit(k, v)));
+    duplicate_writes
+      .into_iter()
+      .for_each(|ent| process_entry(&mut entries, ent));
+
+    // CommitTs should not be zero if we're inserting transaction markers.
+    assert_ne!(commit_ts, 0);
+
+    entries
+  }
+}
+
+impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
+where
+  S: AsyncSpawner,
+{
+  fn done_read(&mut self) {
+    if !self.done_read {
+      self.done_read = true;
+      self.orc().done_read(self.read_ts);
+    }
+  }
 
   #[inline]
   fn orc(&self) -> &Oracle<C, S> {
@@ -949,7 +1894,12 @@ where
       return;
     }
     self.discarded = true;
+    if !self.committed {
+      self.orc.stats.record_discard();
+    }
+    self.run_cleanups(false);
     self.done_read();
+    self.orc.end_txn();
   }
 
   /// Returns true if the transaction is discarded.
@@ -959,6 +1909,65 @@ where
   }
 }
 
+impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
+where
+  C: AsyncCm<Key = K>,
+  P: AsyncPwmRange<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Retracts the pending writes of the keys in the range, without adding delete markers
+  /// for them, returns the number of the retracted keys.
+  ///
+  /// The keys are still marked as conflict keys, as the conflict manager
+  /// does not support unmarking.
+  pub async fn remove_pending_range<R>(
+    &mut self,
+    range: R,
+  ) -> Result<usize, TransactionError<C::Error, P::Error>>
+  where
+    K: Ord + Clone,
+    R: RangeBounds<K>,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .range((range.start_bound(), range.end_bound()))
+      .await
+      .into_iter()
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    for key in keys.iter() {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(key)
+        .await
+        .map_err(TransactionError::Pwm)?
+      {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+      }
+    }
+
+    let size = &mut self.size;
+    let count = &mut self.count;
+    self.duplicate_writes.retain(|ent| {
+      if !range.contains(ent.key()) {
+        return true;
+      }
+      *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+      *count = count.saturating_sub(1);
+      false
+    });
+    Ok(keys.len())
+  }
+}
+
 impl<K, V, C, P, S> Drop for AsyncWtm<K, V, C, P, S>
 where
   S: AsyncSpawner,
@@ -1288,6 +2297,70 @@ mod tests {
     );
   }
 
+  #[async_std::test]
+  async fn remove_pending_insert() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("0".into(), 0).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+
+    // the remove has the same version as the insert it overwrites, so the insert is not
+    // kept as a duplicate write and applied after the remove.
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.remove("1".into()).await.unwrap();
+    wtm.insert_blocking("2".into(), 2).unwrap();
+    wtm.remove_blocking("2".into()).unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|entries| async move {
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+          .iter()
+          .all(|ent| matches!(ent.data, EntryData::Remove(_))));
+        Ok(())
+      })
+      .await
+      .unwrap();
+  }
+
+  #[async_std::test]
+  async fn get_pending_remove() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+
+    // the remove entry is returned, so the caller does not fall back to the committed value.
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    let key = "1".to_owned();
+    wtm.remove(key.clone()).await.unwrap();
+    assert!(wtm.get(&key).await.unwrap().unwrap().value().is_none());
+    assert!(wtm.get_blocking(&key).unwrap().unwrap().value().is_none());
+    assert!(wtm
+      .get_equivalent_cm_comparable_pm(&key)
+      .await
+      .unwrap()
+      .unwrap()
+      .value()
+      .is_none());
+  }
+
   #[async_std::test]
   async fn wtm4() {
     let tm = AsyncTm::<
@@ -1365,4 +2438,1035 @@ mod tests {
     assert_eq!(wtm.contains_key_comparable_blocking(&six).unwrap(), None);
     assert_eq!(wtm.get_comparable_blocking(&six).unwrap(), None);
   }
+
+  #[async_std::test]
+  async fn link_external_resource() {
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let committed = Arc::new(AtomicU8::new(0));
+    let aborted = Arc::new(AtomicU8::new(0));
+    let cleanup = |committed: Arc<AtomicU8>, aborted: Arc<AtomicU8>| {
+      move |ok: bool| {
+        if ok {
+          committed.fetch_add(1, Ordering::SeqCst);
+        } else {
+          aborted.fetch_add(1, Ordering::SeqCst);
+        }
+      }
+    };
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.link_external_resource(cleanup(committed.clone(), aborted.clone()));
+    wtm
+      .commit_with_resource_cleanup::<_, _, _, Infallible>(
+        |_| async { Ok(()) },
+        cleanup(committed.clone(), aborted.clone()),
+      )
+      .await
+      .unwrap();
+    assert_eq!(committed.load(Ordering::SeqCst), 2);
+    assert_eq!(aborted.load(Ordering::SeqCst), 0);
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    wtm.link_external_resource(cleanup(committed.clone(), aborted.clone()));
+    drop(wtm);
+    assert_eq!(committed.load(Ordering::SeqCst), 2);
+    assert_eq!(aborted.load(Ordering::SeqCst), 1);
+  }
+
+  #[async_std::test]
+  async fn on_commit() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let observed = Arc::new(AtomicU64::new(0));
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    let o = observed.clone();
+    wtm.on_commit(move |ts| {
+      o.store(ts, Ordering::SeqCst);
+    });
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+    assert_eq!(observed.load(Ordering::SeqCst), 1);
+    assert_eq!(tm.version().await, 1);
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    let o = observed.clone();
+    wtm.on_commit(move |ts| {
+      o.store(ts, Ordering::SeqCst);
+    });
+    wtm
+      .commit(|_| async { Err::<(), _>(std::io::Error::other("apply failed")) })
+      .await
+      .unwrap_err();
+    assert_eq!(observed.load(Ordering::SeqCst), 1);
+  }
+
+  #[async_std::test]
+  async fn wait_for_version() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+
+    let wtm = tm.write((), Default::default()).await.unwrap();
+    wtm
+      .wait_for_version(1, core::future::pending())
+      .await
+      .unwrap();
+    assert_eq!(
+      wtm.wait_for_version(2, async {}).await.unwrap_err(),
+      TransactionError::Timeout
+    );
+  }
+
+  #[async_std::test]
+  async fn active_transaction_count() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut txns = Vec::new();
+    for i in 0..10u64 {
+      let mut wtm = tm.write((), Default::default()).await.unwrap();
+      wtm.insert(i.to_string(), i).await.unwrap();
+      txns.push(wtm);
+    }
+    assert_eq!(tm.active_transaction_count(), 10);
+
+    for mut wtm in txns {
+      wtm
+        .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+        .await
+        .unwrap();
+    }
+    assert_eq!(tm.active_transaction_count(), 0);
+
+    let wtm = tm.write((), Default::default()).await.unwrap();
+    assert_eq!(tm.active_transaction_count(), 1);
+    drop(wtm);
+    assert_eq!(tm.active_transaction_count(), 0);
+  }
+
+  #[async_std::test]
+  async fn revert_key() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    assert!(wtm.revert_key(&"1".to_owned()).await.unwrap());
+    assert!(!wtm.revert_key(&"1".to_owned()).await.unwrap());
+    assert!(wtm.revert_key_blocking(&"2".to_owned()).unwrap());
+    assert!(!wtm.revert_key_blocking(&"3".to_owned()).unwrap());
+    assert_eq!(wtm.count, 0);
+    assert_eq!(wtm.size, 0);
+    assert_eq!(wtm.contains_key(&"1".to_owned()).await.unwrap(), None);
+    assert!(wtm.pwm().unwrap().is_empty());
+  }
+
+  #[async_std::test]
+  async fn revert_key_duplicate_writes() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut discarded = tm.write((), Default::default()).await.unwrap();
+    discarded.insert("1".into(), 1).await.unwrap();
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    wtm
+      .commit(|_| async { Ok::<_, Infallible>(()) })
+      .await
+      .unwrap();
+
+    // the absorbed write has an older version, so it is kept as a duplicate write.
+    let mut wtm = tm
+      .absorb_discard(discarded, 1, Default::default())
+      .await
+      .unwrap();
+    wtm.insert("1".into(), 2).await.unwrap();
+    assert_eq!(wtm.duplicate_writes.len(), 1);
+    assert!(wtm.guarantee_unique_keys().is_err());
+
+    assert!(wtm.revert_key(&"1".to_owned()).await.unwrap());
+    assert!(wtm.duplicate_writes.is_empty());
+    assert_eq!(wtm.count, 0);
+    assert_eq!(wtm.size, 0);
+
+    wtm.insert("1".into(), 3).await.unwrap();
+    // the overwrite is still counted after the key is reverted.
+    assert!(wtm.guarantee_unique_keys().is_err());
+    assert_eq!(wtm.count, 1);
+  }
+
+  #[async_std::test]
+  async fn set_initial_version() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    tm.set_initial_version(100).await.unwrap();
+    assert_eq!(tm.version().await, 100);
+    assert_eq!(
+      tm.set_initial_version(100).await.unwrap_err(),
+      OracleError::InvalidVersion
+    );
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    assert_eq!(
+      tm.set_initial_version(200).await.unwrap_err(),
+      OracleError::ActiveTransactions
+    );
+    wtm.insert("a".into(), 1).await.unwrap();
+    wtm
+      .commit(|_| async { Ok::<_, Infallible>(()) })
+      .await
+      .unwrap();
+    assert_eq!(tm.version().await, 101);
+    drop(wtm);
+
+    let rtm = tm.read().await;
+    assert_eq!(
+      tm.set_initial_version(200).await.unwrap_err(),
+      OracleError::ActiveTransactions
+    );
+    drop(rtm);
+    tm.set_initial_version(200).await.unwrap();
+    assert_eq!(tm.version().await, 200);
+  }
+
+  #[async_std::test]
+  async fn force_advance() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let handles = (0..2)
+      .map(|i| {
+        let tm = tm.clone();
+        async_std::task::spawn(async move {
+          let mut last = 0;
+          for ts in (1..=100).map(|ts| ts * 2 + i) {
+            tm.force_advance(ts).await.unwrap();
+            let version = tm.version().await;
+            assert!(version >= ts && version >= last);
+            last = version;
+          }
+        })
+      })
+      .collect::<Vec<_>>();
+    for h in handles {
+      h.await;
+    }
+    assert_eq!(tm.version().await, 201);
+
+    // never go backwards
+    tm.force_advance(10).await.unwrap();
+    assert_eq!(tm.version().await, 201);
+    assert_eq!(
+      tm.force_advance(u64::MAX).await.unwrap_err(),
+      error::OracleError::Overflow
+    );
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    assert_eq!(wtm.version(), 201);
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|ents| async move {
+        assert_eq!(ents[0].version(), 202);
+        Ok(())
+      })
+      .await
+      .unwrap();
+    assert_eq!(tm.version().await, 202);
+  }
+
+  #[async_std::test]
+  async fn preview_commit_entries() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    let previewed = wtm
+      .preview_commit_entries::<_, _, _, _, _, Infallible>(
+        |ents, ts| {
+          let keys = ents
+            .iter()
+            .map(|ent| (ent.key().clone(), ent.version()))
+            .collect::<Vec<_>>();
+          async move { (keys, ts) }
+        },
+        |ents| async move {
+          assert_eq!(ents.len(), 2);
+          Ok(())
+        },
+      )
+      .await
+      .unwrap();
+    assert_eq!(
+      previewed,
+      (vec![("1".to_owned(), 1), ("2".to_owned(), 1)], 1)
+    );
+    assert_eq!(tm.version().await, 1);
+  }
+
+  #[cfg(feature = "ttl")]
+  #[async_std::test]
+  async fn insert_with_ttl() {
+    use core::time::Duration;
+
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm
+      .insert_with_ttl("1".into(), 1, Duration::from_secs(1))
+      .await
+      .unwrap();
+    wtm
+      .insert_with_ttl_blocking("2".into(), 2, Duration::from_secs(2))
+      .unwrap();
+    wtm.insert("3".into(), 3).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|ents| async move {
+        let ttls = ents.iter().map(|ent| ent.ttl()).collect::<Vec<_>>();
+        assert_eq!(
+          ttls,
+          vec![
+            Some(Duration::from_secs(1)),
+            Some(Duration::from_secs(2)),
+            None
+          ]
+        );
+        Ok(())
+      })
+      .await
+      .unwrap();
+  }
+
+  #[async_std::test]
+  async fn into_iter() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+    assert_eq!((&wtm).into_iter().count(), 0);
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    wtm.remove("3".into()).await.unwrap();
+    let pendings = (&wtm)
+      .into_iter()
+      .map(|(k, v)| (k.as_str(), v.value))
+      .collect::<Vec<_>>();
+    assert_eq!(pendings, vec![("2", Some(2)), ("3", None)]);
+  }
+
+  #[async_std::test]
+  async fn commit_batch() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut a = tm.write((), Default::default()).await.unwrap();
+    a.insert("1".into(), 1).await.unwrap();
+    let mut b = tm.write((), Default::default()).await.unwrap();
+    b.insert("2".into(), 2).await.unwrap();
+
+    let timestamps = tm
+      .commit_batch::<_, _, Infallible>(vec![a, b], |batch| async move {
+        let keys = batch
+          .iter()
+          .map(|ents| ents.iter().map(|ent| ent.key().clone()).collect::<Vec<_>>())
+          .collect::<Vec<_>>();
+        assert_eq!(keys, vec![vec!["1".to_string()], vec!["2".to_string()]]);
+        Ok(())
+      })
+      .await
+      .unwrap();
+    assert_eq!(timestamps.len(), 2);
+    assert!(timestamps[0] < timestamps[1]);
+    assert_eq!(tm.active_transaction_count(), 0);
+
+    // `b` reads the key written by `a`, which is committed before `b` in the batch.
+    let opts = HashCmOptions::default();
+    let mut a = tm.write((), opts.clone()).await.unwrap();
+    a.insert("3".into(), 3).await.unwrap();
+    let mut b = tm.write((), opts).await.unwrap();
+    b.get(&"3".to_string()).await.unwrap();
+    b.insert("4".into(), 4).await.unwrap();
+
+    let err = tm
+      .commit_batch::<_, _, Infallible>(vec![a, b], |_| async { panic!("should not apply") })
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      WtmError::Transaction(TransactionError::Conflict)
+    ));
+    assert_eq!(tm.active_transaction_count(), 0);
+  }
+
+  #[async_std::test]
+  async fn commit_batch_overflow() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+    tm.force_advance(u64::MAX - 1).await.unwrap();
+    let version = tm.version().await;
+
+    let mut a = tm.write((), Default::default()).await.unwrap();
+    a.insert("1".into(), 1).await.unwrap();
+    let mut b = tm.write((), Default::default()).await.unwrap();
+    b.insert("2".into(), 2).await.unwrap();
+
+    let err = tm
+      .commit_batch::<_, _, Infallible>(vec![a, b], |_| async { panic!("should not apply") })
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      WtmError::Transaction(TransactionError::InvalidVersion)
+    ));
+    assert_eq!(tm.version().await, version);
+    assert_eq!(tm.active_transaction_count(), 0);
+  }
+
+  #[async_std::test]
+  async fn statistics() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+    let opts = HashCmOptions::default();
+
+    let mut reader = tm.write((), opts.clone()).await.unwrap();
+    reader.get(&"1".to_string()).await.unwrap();
+    reader.insert("2".into(), 2).await.unwrap();
+
+    let mut writer = tm.write((), opts).await.unwrap();
+    writer.insert("1".into(), 1).await.unwrap();
+    writer
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+
+    reader
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap_err();
+    // the reads of a discarded transaction are not counted.
+    assert_eq!(
+      reader.get(&"1".to_string()).await.unwrap_err(),
+      TransactionError::Discard
+    );
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.get_blocking(&"1".to_string()).unwrap();
+    drop(wtm);
+
+    assert_eq!(
+      tm.statistics(),
+      TransactionStats {
+        total_commits: 1,
+        total_conflicts: 1,
+        total_discards: 2,
+        total_reads: 2,
+      }
+    );
+  }
+
+  #[async_std::test]
+  async fn fork() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    let mut forked = wtm.fork().await.unwrap();
+    assert_eq!(tm.active_transaction_count(), 2);
+    assert_eq!(forked.version(), wtm.version());
+
+    forked.insert("2".into(), 2).await.unwrap();
+    assert!(wtm.get(&"2".to_string()).await.unwrap().is_none());
+    let keys = forked
+      .commit::<_, _, _, Infallible>(|ents| async move {
+        Ok(ents.iter().map(|ent| ent.key().clone()).collect::<Vec<_>>())
+      })
+      .await
+      .unwrap();
+    assert_eq!(keys, vec!["1".to_string(), "2".to_string()]);
+
+    // `wtm` has read the key written by the committed fork.
+    let err = wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      err,
+      WtmError::Transaction(TransactionError::Conflict)
+    ));
+    assert_eq!(tm.active_transaction_count(), 0);
+    assert!(matches!(wtm.fork().await, Err(TransactionError::Discard)));
+  }
+
+  #[async_std::test]
+  async fn active_read_version() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+    assert_eq!(tm.max_active_read_version(), 0);
+    assert_eq!(tm.min_active_read_version(), None);
+
+    let old = tm.read().await;
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+
+    let new = tm.read().await;
+    assert_eq!(tm.max_active_read_version(), new.version());
+    assert_eq!(tm.min_active_read_version(), Some(old.version()));
+    assert!(old.version() < new.version());
+
+    drop(old);
+    assert_eq!(tm.min_active_read_version(), Some(new.version()));
+    drop(new);
+    assert_eq!(tm.max_active_read_version(), 0);
+    assert_eq!(tm.min_active_read_version(), None);
+  }
+
+  #[async_std::test]
+  async fn apply_if_fresh() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut stale = tm.write((), Default::default()).await.unwrap();
+    stale.insert("1".into(), 1).await.unwrap();
+    stale.apply_if_fresh(0).await.unwrap();
+
+    for i in 0..2 {
+      let mut wtm = tm.write((), Default::default()).await.unwrap();
+      wtm.insert(i.to_string(), i).await.unwrap();
+      wtm
+        .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+        .await
+        .unwrap();
+    }
+
+    stale.apply_if_fresh(2).await.unwrap();
+    assert!(matches!(
+      stale.apply_if_fresh(1).await,
+      Err(TransactionError::StaleTransaction { lag: 2 })
+    ));
+  }
+
+  #[async_std::test]
+  async fn pending_counts() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert("1".into(), 2).await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    wtm.remove("3".into()).await.unwrap();
+    assert_eq!(wtm.pending_inserts_count().await, 2);
+    assert_eq!(wtm.pending_removes_count().await, 1);
+    assert_eq!(wtm.pending_inserts_count_blocking(), 2);
+    assert_eq!(wtm.pending_removes_count_blocking(), 1);
+
+    wtm
+      .commit::<_, _, _, Infallible>(|_| async { Ok(()) })
+      .await
+      .unwrap();
+    assert_eq!(wtm.pending_inserts_count().await, 0);
+    assert_eq!(wtm.pending_removes_count().await, 0);
+  }
+
+  #[async_std::test]
+  async fn get_ref() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.remove("2".into()).await.unwrap();
+    let ent = wtm.get_ref(&"1".to_string()).unwrap().unwrap();
+    assert_eq!(ent.value(), Some(&1));
+    assert_eq!(ent.version(), wtm.version());
+    assert!(wtm.get_ref(&"2".to_string()).unwrap().is_none());
+    assert!(wtm.get_ref(&"3".to_string()).unwrap().is_none());
+
+    wtm.discard();
+    assert!(matches!(
+      wtm.get_ref(&"1".to_string()),
+      Err(TransactionError::Discard)
+    ));
+  }
+
+  #[async_std::test]
+  async fn remaining() {
+    struct LimitedPwm(BTreePwm<String, u64>);
+
+    impl Pwm for LimitedPwm {
+      type Error = Infallible;
+      type Key = String;
+      type Value = u64;
+      type Iter<'a> = <BTreePwm<String, u64> as Pwm>::Iter<'a>;
+      type IntoIter = <BTreePwm<String, u64> as Pwm>::IntoIter;
+      type Options = ();
+
+      fn new(_: Self::Options) -> Result<Self, Self::Error> {
+        Ok(Self(BTreePwm::new()))
+      }
+
+      fn is_empty(&self) -> bool {
+        self.0.is_empty()
+      }
+
+      fn len(&self) -> usize {
+        self.0.len()
+      }
+
+      fn validate_entry(&self, _: &Entry<String, u64>) -> Result<(), Self::Error> {
+        Ok(())
+      }
+
+      fn max_batch_size(&self) -> u64 {
+        100
+      }
+
+      fn max_batch_entries(&self) -> u64 {
+        10
+      }
+
+      fn estimate_size(&self, _: &Entry<String, u64>) -> u64 {
+        10
+      }
+
+      fn get(&self, key: &String) -> Result<Option<&EntryValue<u64>>, Self::Error> {
+        Pwm::get(&self.0, key)
+      }
+
+      fn get_entry(
+        &self,
+        key: &String,
+      ) -> Result<Option<(&String, &EntryValue<u64>)>, Self::Error> {
+        Pwm::get_entry(&self.0, key)
+      }
+
+      fn contains_key(&self, key: &String) -> Result<bool, Self::Error> {
+        Pwm::contains_key(&self.0, key)
+      }
+
+      fn insert(&mut self, key: String, value: EntryValue<u64>) -> Result<(), Self::Error> {
+        Pwm::insert(&mut self.0, key, value)
+      }
+
+      fn remove_entry(
+        &mut self,
+        key: &String,
+      ) -> Result<Option<(String, EntryValue<u64>)>, Self::Error> {
+        Pwm::remove_entry(&mut self.0, key)
+      }
+
+      fn iter(&self) -> Self::Iter<'_> {
+        Pwm::iter(&self.0)
+      }
+
+      fn into_iter(self) -> Self::IntoIter {
+        Pwm::into_iter(self.0)
+      }
+
+      fn rollback(&mut self) -> Result<(), Self::Error> {
+        Pwm::rollback(&mut self.0)
+      }
+    }
+
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, LimitedPwm, wmark::AsyncStdSpawner>::new("test", 0)
+        .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    assert_eq!(wtm.max_batch_entries(), 10);
+    assert_eq!(wtm.max_batch_size(), 100);
+    assert_eq!(wtm.remaining_entries(), 9);
+    assert_eq!(
+      wtm.remaining_size(),
+      100 - 1 - <LimitedPwm as Pwm>::VERSION_OVERHEAD
+    );
+
+    // every entry takes 10 bytes plus the version overhead.
+    let mut i = 0;
+    while wtm.remaining_size() >= 10 {
+      wtm.insert(i.to_string(), i).await.unwrap();
+      i += 1;
+    }
+    assert_eq!(i, 3);
+    assert_eq!(
+      wtm.estimate_total_commit_size(),
+      3 * (10 + <LimitedPwm as Pwm>::VERSION_OVERHEAD)
+    );
+    assert_eq!(wtm.remaining_entries(), 6);
+    assert!(matches!(
+      wtm.insert(i.to_string(), i).await,
+      Err(TransactionError::LargeTxn)
+    ));
+  }
+
+  #[async_std::test]
+  #[cfg(feature = "capacity-warn")]
+  async fn capacity_warn() {
+    use std::sync::Mutex;
+
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let warned = Arc::new(Mutex::new(Vec::new()));
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("0".into(), 0).await.unwrap();
+
+    let w = warned.clone();
+    wtm.set_capacity_warn_threshold(u64::MAX, move |entries, size| {
+      w.lock().unwrap().push((entries, size));
+    });
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert_blocking("2".into(), 2).unwrap();
+
+    let warned = warned.lock().unwrap();
+    assert_eq!(warned.len(), 2);
+    assert_eq!(warned[0], (wtm.remaining_entries() + 1, warned[0].1));
+    assert_eq!(warned[1], (wtm.remaining_entries(), wtm.remaining_size()));
+    assert!(warned[0].1 > warned[1].1);
+  }
+
+  #[async_std::test]
+  async fn close_gracefully() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+
+    assert_eq!(
+      tm.close_gracefully(async {}).await,
+      Err(OracleError::Timeout)
+    );
+    assert!(matches!(
+      tm.write((), Default::default()).await,
+      Err(TransactionError::Closed)
+    ));
+    assert_eq!(tm.active_transaction_count(), 1);
+
+    // the active transaction can still be committed after the manager is closed.
+    wtm
+      .commit(|_| async { Ok::<_, std::io::Error>(()) })
+      .await
+      .unwrap();
+    drop(wtm);
+
+    tm.close_gracefully(futures::future::pending())
+      .await
+      .unwrap();
+    assert_eq!(tm.active_transaction_count(), 0);
+  }
+
+  #[async_std::test]
+  async fn pending_iter_rev() {
+    async fn check<P: AsyncPwm<Key = u64, Value = u64>>(mut pwm: P) {
+      for k in [2, 3, 1] {
+        let (key, value) = Entry::new(0, EntryData::Insert { key: k, value: k }).split();
+        pwm.insert(key, value).await.unwrap();
+      }
+      let keys = pwm.iter_rev().await.map(|(k, _)| *k).collect::<Vec<_>>();
+      assert_eq!(keys, vec![3, 2, 1]);
+    }
+
+    check(BTreePwm::<u64, u64>::new()).await;
+    check(IndexMapPwm::<u64, u64>::default()).await;
+  }
+
+  #[async_std::test]
+  async fn drain_removes() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    let size = wtm.estimate_total_commit_size();
+    wtm.remove("2".into()).await.unwrap();
+    wtm.remove("4".into()).await.unwrap();
+    assert_eq!(wtm.drain_removes().await.unwrap(), vec!["2", "4"]);
+
+    wtm.remove_blocking("3".into()).unwrap();
+    assert_eq!(wtm.drain_removes_blocking().unwrap(), vec!["3"]);
+    assert!(wtm.drain_removes().await.unwrap().is_empty());
+    assert_eq!(wtm.estimate_total_commit_size(), size);
+    assert!(wtm.get(&"1".to_owned()).await.unwrap().is_some());
+
+    // the older removes kept as duplicate writes are drained too.
+    let version = wtm.version();
+    wtm.remove("5".into()).await.unwrap();
+    wtm.__set_read_version(version + 1);
+    wtm.insert("5".into(), 5).await.unwrap();
+    assert_eq!(wtm.duplicate_write_count(), 1);
+    let size = wtm.estimate_total_commit_size();
+    assert!(wtm.drain_removes().await.unwrap().is_empty());
+    assert_eq!(wtm.duplicate_write_count(), 0);
+    assert!(wtm.estimate_total_commit_size() < size);
+
+    wtm.remove_blocking("6".into()).unwrap();
+    wtm.__set_read_version(version + 2);
+    wtm.insert("6".into(), 6).await.unwrap();
+    wtm.__set_read_version(version);
+    assert_eq!(wtm.duplicate_write_count(), 1);
+    assert!(wtm.drain_removes_blocking().unwrap().is_empty());
+    assert_eq!(wtm.duplicate_write_count(), 0);
+    assert_eq!(wtm.total_entry_count().await, 3);
+  }
+
+  #[async_std::test]
+  async fn version_gap_warning() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let gaps = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let recorded = gaps.clone();
+    tm.set_version_gap_warning_threshold(1, move |gap| recorded.lock().unwrap().push(gap));
+
+    let rtm = tm.read().await;
+    assert_eq!(rtm.version(), 0);
+
+    for i in 0..3u64 {
+      let mut wtm = tm.write((), Default::default()).await.unwrap();
+      wtm.insert(i.to_string(), i).await.unwrap();
+      wtm
+        .commit(|_| async { Ok::<_, std::io::Error>(()) })
+        .await
+        .unwrap();
+    }
+    assert_eq!(*gaps.lock().unwrap(), vec![2, 3]);
+
+    drop(rtm);
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("4".into(), 4).await.unwrap();
+    wtm
+      .commit(|_| async { Ok::<_, std::io::Error>(()) })
+      .await
+      .unwrap();
+    assert_eq!(*gaps.lock().unwrap(), vec![2, 3]);
+  }
+
+  #[async_std::test]
+  async fn pending_to_vec() {
+    async fn check<P: AsyncPwm<Key = u64, Value = u64>>(mut pwm: P) {
+      for k in [2, 3, 1] {
+        let (key, value) = Entry::new(0, EntryData::Insert { key: k, value: k }).split();
+        pwm.insert(key, value).await.unwrap();
+      }
+      pwm.remove_entry(&3).await.unwrap();
+
+      let mut entries = pwm.to_vec().await;
+      entries.sort_unstable_by_key(|(k, _)| *k);
+      assert_eq!(
+        entries
+          .iter()
+          .map(|(k, v)| (*k, v.value))
+          .collect::<Vec<_>>(),
+        vec![(1, Some(1)), (2, Some(2))]
+      );
+      assert_eq!(pwm.len().await, 2);
+
+      let mut drained = pwm.drain_to_vec().await;
+      drained.sort_unstable_by_key(|(k, _)| *k);
+      assert_eq!(drained, entries);
+    }
+
+    check(BTreePwm::<u64, u64>::new()).await;
+    check(IndexMapPwm::<u64, u64>::default()).await;
+  }
+
+  #[async_std::test]
+  async fn total_entry_count() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    let version = wtm.version();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert("1".into(), 2).await.unwrap();
+    wtm.insert_blocking("2".into(), 2).unwrap();
+    assert_eq!(wtm.duplicate_write_count(), 0);
+    assert_eq!(wtm.total_entry_count().await, 2);
+
+    // a write of the same key at another version is kept as a duplicate write.
+    wtm.__set_read_version(version + 1);
+    wtm.insert("1".into(), 3).await.unwrap();
+    wtm.__set_read_version(version);
+    assert_eq!(wtm.duplicate_write_count(), 1);
+    assert_eq!(wtm.total_entry_count().await, 3);
+    assert_eq!(wtm.total_entry_count_blocking(), 3);
+  }
+
+  #[async_std::test]
+  async fn read_own_writes() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    assert!(wtm.read_own_writes());
+    wtm.insert("1".into(), 1).await.unwrap();
+    let key = "1".to_owned();
+    assert!(wtm.get(&key).await.unwrap().is_some());
+    assert_eq!(wtm.contains_key(&key).await.unwrap(), Some(true));
+    assert!(wtm.get_blocking(&key).unwrap().is_some());
+
+    wtm.set_read_own_writes(false);
+    assert!(wtm.get(&key).await.unwrap().is_none());
+    assert_eq!(wtm.contains_key(&key).await.unwrap(), None);
+    assert!(wtm.get_blocking(&key).unwrap().is_none());
+    assert_eq!(wtm.contains_key_blocking(&key).unwrap(), None);
+  }
+
+  #[async_std::test]
+  async fn guarantee_unique_keys() {
+    let tm =
+      AsyncTm::<String, u64, HashCm<String>, BTreePwm<String, u64>, wmark::AsyncStdSpawner>::new(
+        "test", 0,
+      )
+      .await;
+
+    let mut wtm = tm.write((), Default::default()).await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.insert_blocking("2".into(), 2).unwrap();
+    wtm.guarantee_unique_keys().unwrap();
+
+    wtm.remove_blocking("1".into()).unwrap();
+    assert_eq!(
+      wtm.guarantee_unique_keys().unwrap_err(),
+      TransactionError::DuplicateKey
+    );
+
+    // draining the pending writes resets the check.
+    wtm.pending_entries_drain().await.unwrap();
+    wtm.insert("1".into(), 1).await.unwrap();
+    wtm.guarantee_unique_keys().unwrap();
+
+    // retracting the overwritten key does not reset the check.
+    wtm.insert("1".into(), 2).await.unwrap();
+    assert!(wtm.revert_key(&"1".to_owned()).await.unwrap());
+    assert_eq!(
+      wtm.guarantee_unique_keys().unwrap_err(),
+      TransactionError::DuplicateKey
+    );
+
+    wtm.pending_entries_drain().await.unwrap();
+    wtm.insert("2".into(), 2).await.unwrap();
+    wtm.insert("2".into(), 3).await.unwrap();
+    assert_eq!(
+      wtm
+        .remove_pending_range("1".to_owned().."3".to_owned())
+        .await
+        .unwrap(),
+      1
+    );
+    assert_eq!(
+      wtm.guarantee_unique_keys().unwrap_err(),
+      TransactionError::DuplicateKey
+    );
+  }
 }
@@ -1,3 +1,7 @@
+use core::{convert::Infallible, hash::BuildHasher};
+
+use txn_core::sync::CmIter;
+
 use super::*;
 
 impl<K, V, C, P, S> AsyncTm<K, V, C, P, S>
@@ -14,7 +18,7 @@ where
     conflict_manager_opts: C::Options,
   ) -> Result<AsyncWtm<K, V, C, P, S>, TransactionError<C::Error, P::Error>> {
     let read_ts = self.inner.read_ts().await;
-    Ok(AsyncWtm {
+    let wtm = AsyncWtm {
       orc: self.inner.clone(),
       read_ts,
       size: 0,
@@ -24,7 +28,43 @@ where
       duplicate_writes: OneOrMore::new(),
       discarded: false,
       done_read: false,
-    })
+      cleanups: Vec::new(),
+      commit_hooks: Vec::new(),
+      committed: false,
+      conflict_keys_limit: None,
+      read_own_writes: true,
+      overwrites: 0,
+      retry_budget: None,
+      retry_count: 0,
+      #[cfg(feature = "capacity-warn")]
+      capacity_warn: None,
+    };
+    if !self.inner.begin_txn() {
+      return Err(TransactionError::Closed);
+    }
+    Ok(wtm)
+  }
+}
+
+/// Iterates over the pending writes of the transaction, the committed data in the
+/// database is never observed. A discarded transaction yields nothing.
+impl<'a, K, V, C, P, S> IntoIterator for &'a AsyncWtm<K, V, C, P, S>
+where
+  P: Pwm<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  type Item = (&'a K, &'a EntryValue<V>);
+
+  type IntoIter = core::iter::Flatten<core::option::IntoIter<P::Iter<'a>>>;
+
+  #[inline]
+  fn into_iter(self) -> Self::IntoIter {
+    self
+      .pending_writes
+      .as_ref()
+      .map(Pwm::iter)
+      .into_iter()
+      .flatten()
   }
 }
 
@@ -132,6 +172,160 @@ where
   P: Pwm<Key = K, Value = V>,
   S: AsyncSpawner,
 {
+  /// Looks for the key in the pending writes only, the key is not marked as read.
+  ///
+  /// Returns `None` if the key is not in the pending writes, or is a pending remove.
+  pub fn get_ref(
+    &self,
+    key: &K,
+  ) -> Result<Option<EntryRef<'_, K, V>>, TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let ent = self
+      .pending_writes
+      .as_ref()
+      .unwrap()
+      .get_entry(key)
+      .map_err(TransactionError::Pwm)?;
+    Ok(ent.and_then(|(key, ent)| {
+      ent.value.as_ref().map(|value| EntryRef {
+        data: EntryDataRef::Insert { key, value },
+        version: ent.version,
+      })
+    }))
+  }
+
+  /// Returns the number of keys with a pending insert, a key inserted many times is counted once.
+  pub fn pending_inserts_count_blocking(&self) -> usize {
+    self
+      .into_iter()
+      .filter(|(_, ent)| ent.value.is_some())
+      .count()
+  }
+
+  /// Returns the number of entries will be committed, including the
+  /// [duplicate writes](AsyncWtm::duplicate_write_count).
+  pub fn total_entry_count_blocking(&self) -> usize {
+    self.pending_writes.as_ref().map_or(0, |p| p.len()) + self.duplicate_write_count()
+  }
+
+  /// Returns the number of keys with a pending remove.
+  pub fn pending_removes_count_blocking(&self) -> usize {
+    self
+      .into_iter()
+      .filter(|(_, ent)| ent.value.is_none())
+      .count()
+  }
+
+  /// Drains the pending writes and the duplicate writes of the transaction, e.g. to move them
+  /// into another transaction, the transaction stays live and can accept new writes.
+  ///
+  /// The versions of the entries are left as they are written. The drained keys are still
+  /// marked as conflict keys, as the conflict manager does not support unmarking.
+  pub fn pending_entries_drain_blocking(
+    &mut self,
+  ) -> Result<OneOrMore<Entry<K, V>>, TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    let mut entries = mem::take(&mut self.duplicate_writes);
+    for key in keys {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(&key)
+        .map_err(TransactionError::Pwm)?
+      {
+        entries.push(Entry::unsplit(k, v));
+      }
+    }
+    self.count = 0;
+    self.size = 0;
+    self.overwrites = 0;
+    Ok(entries)
+  }
+
+  /// Drains the pending removes of the transaction and returns their keys, e.g. to replicate
+  /// the removes separately from the inserts.
+  ///
+  /// The removes at older versions kept as duplicate writes are dropped as well, their keys
+  /// have a newer pending write, which is drained here if it is a remove too.
+  ///
+  /// The drained keys are still marked as conflict keys, as the conflict manager does not
+  /// support unmarking.
+  pub fn drain_removes_blocking(&mut self) -> Result<Vec<K>, TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .filter(|(_, v)| v.value.is_none())
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    for key in keys.iter() {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(key)
+        .map_err(TransactionError::Pwm)?
+      {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+      }
+    }
+
+    let size = &mut self.size;
+    let count = &mut self.count;
+    self.duplicate_writes.retain(|ent| {
+      if !matches!(ent.data, EntryData::Remove(_)) {
+        return true;
+      }
+      *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+      *count = count.saturating_sub(1);
+      false
+    });
+    Ok(keys)
+  }
+
+  /// Consumes the transaction and returns its write set without committing it, e.g. to ship
+  /// the writes to another replica, the transaction is discarded.
+  ///
+  /// The versions of the entries are left as they are written, the read version of the
+  /// transaction, as no commit version is assigned.
+  pub fn into_entries_blocking(
+    mut self,
+  ) -> Result<OneOrMore<Entry<K, V>>, TransactionError<C::Error, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = mem::take(&mut self.pending_writes).unwrap();
+    let mut entries = mem::take(&mut self.duplicate_writes);
+    pending_writes
+      .drain_to_vec()
+      .into_iter()
+      .for_each(|(k, v)| entries.push(Entry::unsplit(k, v)));
+    self.discard();
+    Ok(entries)
+  }
+
   /// Rolls back the transaction.
   #[inline]
   pub fn rollback_blocking(&mut self) -> Result<(), TransactionError<C::Error, P::Error>> {
@@ -154,6 +348,104 @@ where
     Ok(())
   }
 
+  /// Retracts the pending write of the key, without adding a delete marker for it.
+  ///
+  /// Returns `true` if the key was in the pending writes, `false` otherwise. The duplicate
+  /// writes of the key are retracted as well.
+  ///
+  /// The key is still marked as a conflict key, as the conflict manager
+  /// does not support unmarking.
+  pub fn revert_key_blocking(
+    &mut self,
+    key: &K,
+  ) -> Result<bool, TransactionError<C::Error, P::Error>>
+  where
+    K: Eq,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    match pending_writes
+      .remove_entry(key)
+      .map_err(TransactionError::Pwm)?
+    {
+      Some((k, v)) => {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+
+        let size = &mut self.size;
+        let count = &mut self.count;
+        self.duplicate_writes.retain(|ent| {
+          if ent.key() != key {
+            return true;
+          }
+          *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+          *count = count.saturating_sub(1);
+          false
+        });
+        Ok(true)
+      }
+      None => Ok(false),
+    }
+  }
+
+  /// Applies `f` to the value of every pending insert in place, pending removes are left untouched.
+  ///
+  /// The size and count of the transaction are recomputed afterwards, the limits are not
+  /// checked against them, so a later write may fail with [`TransactionError::LargeTxn`].
+  pub fn map_values_blocking<F>(
+    &mut self,
+    mut f: F,
+  ) -> Result<(), TransactionError<C::Error, P::Error>>
+  where
+    K: Clone,
+    F: FnMut(&K, V) -> V,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .iter()
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    let mut size = 0;
+    for key in keys {
+      if let Some((k, mut v)) = pending_writes
+        .remove_entry(&key)
+        .map_err(TransactionError::Pwm)?
+      {
+        v.value = v.value.map(|value| f(&k, value));
+        let ent = Entry::unsplit(k, v);
+        size += pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
+        let (k, v) = ent.split();
+        pending_writes.insert(k, v).map_err(TransactionError::Pwm)?;
+      }
+    }
+
+    self.duplicate_writes = mem::take(&mut self.duplicate_writes)
+      .into_iter()
+      .map(|ent| {
+        let (k, mut v) = ent.split();
+        v.value = v.value.map(|value| f(&k, value));
+        let ent = Entry::unsplit(k, v);
+        size += pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
+        ent
+      })
+      .collect();
+
+    self.count = (pending_writes.len() + self.duplicate_writes.len()) as u64;
+    self.size = size;
+    Ok(())
+  }
+
   /// Insert a key-value pair to the transaction.
   pub fn insert_blocking(
     &mut self,
@@ -163,16 +455,47 @@ where
     self.insert_with_blocking_in(key, value)
   }
 
+  /// Inserts the pre-built entries to the transaction in order, e.g. the entries decoded from
+  /// a WAL, both the insert and the remove entries are accepted.
+  ///
+  /// The versions of the entries are overwritten with the read version of the transaction,
+  /// as the pending writes are versioned at the commit timestamp. If an entry fails, the
+  /// entries before it stay in the transaction and the error is returned.
+  pub fn insert_many_raw_blocking(
+    &mut self,
+    entries: impl IntoIterator<Item = Entry<K, V>>,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    for mut ent in entries {
+      ent.version = self.read_ts;
+      self.modify_blocking(ent)?;
+    }
+    Ok(())
+  }
+
+  /// Insert a key-value pair with a time-to-live hint to the transaction.
+  ///
+  /// The transaction manager only passes the `ttl` through to [`Entry::ttl`],
+  /// the database is responsible for enforcing it when applying the entries.
+  #[cfg(feature = "ttl")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "ttl")))]
+  pub fn insert_with_ttl_blocking(
+    &mut self,
+    key: K,
+    value: V,
+    ttl: core::time::Duration,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    let mut ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
+    ent.ttl = Some(ttl);
+    self.modify_blocking(ent)
+  }
+
   /// Removes a key.
   ///
   /// This is done by adding a delete marker for the key at commit timestamp.  Any
   /// reads happening before this timestamp would be unaffected. Any reads after
   /// this commit would see the deletion.
   pub fn remove_blocking(&mut self, key: K) -> Result<(), TransactionError<C::Error, P::Error>> {
-    self.modify_blocking(Entry {
-      data: EntryData::Remove(key),
-      version: 0,
-    })
+    self.modify_blocking(Entry::new(self.read_ts, EntryData::Remove(key)))
   }
 
   /// Returns `true` if the pending writes contains the key.
@@ -184,13 +507,17 @@ where
       return Err(TransactionError::Discard);
     }
 
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get(key)
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get(key)
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -214,6 +541,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_blocking<'a, 'b: 'a>(
     &'a mut self,
     key: &'b K,
@@ -221,19 +551,19 @@ where
     if self.discarded {
       return Err(TransactionError::Discard);
     }
+    self.orc.stats.record_read();
 
-    if let Some(e) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get(key)
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get(key)
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some(e) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -258,10 +588,7 @@ where
     key: K,
     value: V,
   ) -> Result<(), TransactionError<C::Error, P::Error>> {
-    let ent = Entry {
-      data: EntryData::Insert { key, value },
-      version: self.read_ts,
-    };
+    let ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
 
     self.modify_blocking(ent)
   }
@@ -269,6 +596,16 @@ where
   fn modify_blocking(
     &mut self,
     ent: Entry<K, V>,
+  ) -> Result<(), TransactionError<C::Error, P::Error>> {
+    self.modify_blocking_with(ent, |conflict_manager, key| {
+      conflict_manager.mark_conflict(key)
+    })
+  }
+
+  fn modify_blocking_with(
+    &mut self,
+    ent: Entry<K, V>,
+    mark_conflict: impl FnOnce(&mut C, &K),
   ) -> Result<(), TransactionError<C::Error, P::Error>> {
     if self.discarded {
       return Err(TransactionError::Discard);
@@ -281,18 +618,29 @@ where
 
     let cnt = self.count + 1;
     // Extra bytes for the version in key.
-    let size = self.size + pending_writes.estimate_size(&ent);
+    let size = self.size + pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
     if cnt >= pending_writes.max_batch_entries() || size >= pending_writes.max_batch_size() {
       return Err(TransactionError::LargeTxn);
     }
 
+    if let Some(limit) = self.conflict_keys_limit {
+      if self.conflict_manager.is_some()
+        && pending_writes.len() >= limit
+        && !pending_writes
+          .contains_key(ent.key())
+          .map_err(TransactionError::Pwm)?
+      {
+        return Err(TransactionError::TooManyConflictKeys);
+      }
+    }
+
     self.count = cnt;
     self.size = size;
 
     // The conflict_manager is used for conflict detection. If conflict detection
     // is disabled, we don't need to store key hashes in the conflict_manager.
     if let Some(ref mut conflict_manager) = self.conflict_manager {
-      conflict_manager.mark_conflict(ent.key());
+      mark_conflict(conflict_manager, ent.key());
     }
 
     // If a duplicate entry was inserted in managed mode, move it to the duplicate writes slice.
@@ -305,6 +653,7 @@ where
       .remove_entry(&ek)
       .map_err(TransactionError::Pwm)?
     {
+      self.overwrites += 1;
       if old_value.version != eversion {
         self
           .duplicate_writes
@@ -315,6 +664,203 @@ where
       .insert(ek, ev)
       .map_err(TransactionError::Pwm)?;
 
+    #[cfg(feature = "capacity-warn")]
+    {
+      let (max_batch_entries, max_batch_size) = (
+        pending_writes.max_batch_entries(),
+        pending_writes.max_batch_size(),
+      );
+      self.warn_capacity(max_batch_entries, max_batch_size, P::VERSION_OVERHEAD);
+    }
+
+    Ok(())
+  }
+}
+
+impl<K, V, H, P, S> AsyncWtm<K, V, HashCm<K, H>, P, S>
+where
+  K: Hash + Eq,
+  H: BuildHasher,
+  P: Pwm<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Inserts the key-value pairs with their pre-computed fingerprints, the keys are not
+  /// hashed again by the conflict manager.
+  ///
+  /// If an insert fails, the pairs before it stay in the transaction.
+  ///
+  /// # Warning
+  /// Every fingerprint must be the one [`HashCm::hasher`] of this transaction computes for
+  /// its key, otherwise conflicts on the key will silently go undetected.
+  pub fn insert_batch_raw_blocking(
+    &mut self,
+    entries: impl IntoIterator<Item = (K, V, u64)>,
+  ) -> Result<(), TransactionError<Infallible, P::Error>> {
+    for (key, value, fp) in entries {
+      let ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
+      self.modify_blocking_with(ent, |conflict_manager, _| {
+        conflict_manager.mark_conflict_fingerprint(fp)
+      })?;
+    }
+    Ok(())
+  }
+
+  /// Exports the state of the transaction, which can be imported by
+  /// [`import_state_blocking`](AsyncWtm::import_state_blocking) to reconstruct the
+  /// transaction on another transaction manager.
+  ///
+  /// The ttl hints of the entries are not exported.
+  pub fn export_state_blocking(
+    &self,
+  ) -> Result<TransactionState<K, V>, TransactionError<Infallible, P::Error>>
+  where
+    K: Clone,
+    V: Clone,
+  {
+    let (cm, pm) = match (&self.conflict_manager, &self.pending_writes) {
+      (Some(cm), Some(pm)) if !self.discarded => (cm, pm),
+      _ => return Err(TransactionError::Discard),
+    };
+
+    Ok(TransactionState {
+      read_version: self.read_ts,
+      reads: cm.reads_iter().collect(),
+      iterated: cm.is_iterated(),
+      conflict_keys: cm.conflict_keys_iter().collect(),
+      pending_writes: Pwm::iter(pm)
+        .map(|(k, ev)| (ev.version, k.clone(), ev.value.clone()))
+        .collect(),
+      duplicate_writes: self
+        .duplicate_writes
+        .iter()
+        .map(|ent| {
+          let (k, ev) = ent.clone().split();
+          (ev.version, k, ev.value)
+        })
+        .collect(),
+    })
+  }
+
+  /// Imports the state exported by [`export_state_blocking`](AsyncWtm::export_state_blocking)
+  /// into this transaction, the state is merged with the reads and writes of this transaction.
+  ///
+  /// The read version of this transaction must be the read version of the state, as the reads
+  /// of the state are checked against the versions after it. Returns
+  /// [`TransactionError::StaleTransaction`] if this transaction reads at a later version, and
+  /// [`TransactionError::InvalidVersion`] if at an earlier one, e.g. the state is not replicated yet.
+  pub fn import_state_blocking(
+    &mut self,
+    state: TransactionState<K, V>,
+  ) -> Result<(), TransactionError<Infallible, P::Error>> {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    if self.read_ts > state.read_version {
+      return Err(TransactionError::StaleTransaction {
+        lag: self.read_ts - state.read_version,
+      });
+    }
+    if self.read_ts < state.read_version {
+      return Err(TransactionError::InvalidVersion);
+    }
+
+    let TransactionState {
+      reads,
+      iterated,
+      conflict_keys,
+      pending_writes,
+      duplicate_writes,
+      ..
+    } = state;
+
+    if let Some(cm) = self.conflict_manager.as_mut() {
+      for fp in reads {
+        cm.mark_read_fingerprint(fp);
+      }
+      if iterated {
+        cm.mark_iter();
+      }
+      for fp in conflict_keys {
+        cm.mark_conflict_fingerprint(fp);
+      }
+    }
+
+    let into_entry = |(version, key, value): (u64, K, Option<V>)| {
+      let data = match value {
+        Some(value) => EntryData::Insert { key, value },
+        None => EntryData::Remove(key),
+      };
+      Entry::new(version, data)
+    };
+    for ent in duplicate_writes.into_iter().map(into_entry) {
+      let pending_writes = self.pending_writes.as_ref().unwrap();
+      self.count += 1;
+      self.size += pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
+      self.duplicate_writes.push(ent);
+    }
+    // the conflict keys are imported above.
+    for ent in pending_writes.into_iter().map(into_entry) {
+      self.modify_blocking_with(ent, |_, _| {})?;
+    }
+    Ok(())
+  }
+
+  /// Moves the pending writes of this transaction into the target transaction, and merges
+  /// the reads and the conflict keys of this transaction into the target. This transaction
+  /// is left with empty pending writes but stays live.
+  ///
+  /// Both transactions must belong to the same transaction manager, returns
+  /// [`TransactionError::Illegal`] otherwise. The target must not read at a later version than
+  /// this transaction, as the merged reads are checked against the versions after the read
+  /// version of the target, returns [`TransactionError::StaleTransaction`] otherwise. If the
+  /// target reads at an earlier version, the versions of the merged writes are rewritten to
+  /// the read version of the target, like its own pending writes. Returns
+  /// [`TransactionError::LargeTxn`] without moving anything if the merged writes may not fit
+  /// into the target.
+  pub fn merge_pending_into_blocking(
+    &mut self,
+    target: &mut Self,
+  ) -> Result<(), TransactionError<Infallible, P::Error>>
+  where
+    K: Clone,
+  {
+    if self.discarded || target.discarded {
+      return Err(TransactionError::Discard);
+    }
+    if !Arc::ptr_eq(&self.orc, &target.orc) {
+      return Err(TransactionError::Illegal);
+    }
+    if target.read_ts > self.read_ts {
+      return Err(TransactionError::StaleTransaction {
+        lag: target.read_ts - self.read_ts,
+      });
+    }
+
+    let target_writes = target.pending_writes.as_ref().unwrap();
+    if target.count + self.count >= target_writes.max_batch_entries()
+      || target.size + self.size >= target_writes.max_batch_size()
+    {
+      return Err(TransactionError::LargeTxn);
+    }
+
+    if let (Some(cm), Some(target_cm)) = (&self.conflict_manager, &mut target.conflict_manager) {
+      for fp in cm.reads_iter() {
+        target_cm.mark_read_fingerprint(fp);
+      }
+      if cm.is_iterated() {
+        target_cm.mark_iter();
+      }
+      for fp in cm.conflict_keys_iter() {
+        target_cm.mark_conflict_fingerprint(fp);
+      }
+    }
+
+    // the conflict keys are merged above.
+    for mut ent in self.pending_entries_drain_blocking()? {
+      ent.version = target.read_ts;
+      target.modify_blocking_with(ent, |_, _| {})?;
+    }
     Ok(())
   }
 }
@@ -338,13 +884,17 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_equivalent(key)
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_equivalent(key)
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -368,6 +918,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_comparable_cm_equivalent_pm_blocking<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -376,18 +929,22 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_equivalent(key)
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_equivalent(key)
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -427,13 +984,17 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_comparable(key)
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_comparable(key)
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -457,6 +1018,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_equivalent_cm_comparable_pm_blocking<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -465,18 +1029,22 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Eq + Ord + Hash,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_comparable(key)
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_comparable(key)
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -516,13 +1084,17 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Ord,
   {
-    match self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_comparable(key)
-      .map_err(TransactionError::pending)?
-    {
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_comparable(key)
+        .map_err(TransactionError::pending)?
+    } else {
+      None
+    };
+    match pending {
       Some(ent) => {
         // If the value is None, it means that the key is removed.
         if ent.value.is_none() {
@@ -546,6 +1118,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_comparable_blocking<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -554,18 +1129,22 @@ where
     K: Borrow<Q>,
     Q: ?Sized + Ord,
   {
-    if let Some((k, e)) = self
-      .pending_writes
-      .as_ref()
-      .unwrap()
-      .get_entry_comparable(key)
-      .map_err(TransactionError::Pwm)?
-    {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+    self.orc.stats.record_read();
 
+    let pending = if self.read_own_writes {
+      self
+        .pending_writes
+        .as_ref()
+        .unwrap()
+        .get_entry_comparable(key)
+        .map_err(TransactionError::Pwm)?
+    } else {
+      None
+    };
+    if let Some((k, e)) = pending {
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -585,3 +1164,60 @@ where
     }
   }
 }
+
+impl<K, V, C, P, S> AsyncWtm<K, V, C, P, S>
+where
+  C: Cm<Key = K>,
+  P: PwmRange<Key = K, Value = V>,
+  S: AsyncSpawner,
+{
+  /// Retracts the pending writes of the keys in the range, without adding delete markers
+  /// for them, returns the number of the retracted keys.
+  ///
+  /// The keys are still marked as conflict keys, as the conflict manager
+  /// does not support unmarking.
+  pub fn remove_pending_range_blocking<R>(
+    &mut self,
+    range: R,
+  ) -> Result<usize, TransactionError<C::Error, P::Error>>
+  where
+    K: Ord + Clone,
+    R: RangeBounds<K>,
+  {
+    if self.discarded {
+      return Err(TransactionError::Discard);
+    }
+
+    let pending_writes = self.pending_writes.as_mut().unwrap();
+    let keys = pending_writes
+      .range((range.start_bound(), range.end_bound()))
+      .into_iter()
+      .map(|(k, _)| k.clone())
+      .collect::<Vec<_>>();
+
+    for key in keys.iter() {
+      if let Some((k, v)) = pending_writes
+        .remove_entry(key)
+        .map_err(TransactionError::Pwm)?
+      {
+        let ent = Entry::unsplit(k, v);
+        self.size = self
+          .size
+          .saturating_sub(pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD);
+        self.count = self.count.saturating_sub(1);
+      }
+    }
+
+    let size = &mut self.size;
+    let count = &mut self.count;
+    self.duplicate_writes.retain(|ent| {
+      if !range.contains(ent.key()) {
+        return true;
+      }
+      *size = size.saturating_sub(pending_writes.estimate_size(ent) + P::VERSION_OVERHEAD);
+      *count = count.saturating_sub(1);
+      false
+    });
+    Ok(keys.len())
+  }
+}
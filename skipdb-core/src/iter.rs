@@ -10,6 +10,8 @@ use crossbeam_skiplist::map::Iter as MapIter;
 pub struct Iter<'a, K, V> {
   pub(crate) iter: MapIter<'a, K, Values<V>>,
   pub(crate) version: u64,
+  // the entries written before this version are skipped.
+  pub(crate) from: u64,
 }
 
 impl<'a, K, V> Iterator for Iter<'a, K, V>
@@ -25,7 +27,7 @@ where
         .value()
         .upper_bound(Bound::Included(&self.version))
         .and_then(|ent| {
-          if ent.value().is_some() {
+          if ent.value().is_some() && *ent.key() >= self.from {
             Some(*ent.key())
           } else {
             None
@@ -41,7 +43,7 @@ where
 /// Iterator over the entries of the write transaction.
 pub struct TransactionIter<'a, K, V, C> {
   committed: Iter<'a, K, V>,
-  pendings: BTreeMapIter<'a, K, EntryValue<V>>,
+  pendings: Option<BTreeMapIter<'a, K, EntryValue<V>>>,
   next_pending: Option<(&'a K, &'a EntryValue<V>)>,
   next_committed: Option<Ref<'a, K, V>>,
   last_yielded_key: Option<Either<&'a K, Ref<'a, K, V>>>,
@@ -54,7 +56,7 @@ where
   K: Ord,
 {
   fn advance_pending(&mut self) {
-    self.next_pending = self.pendings.next();
+    self.next_pending = self.pendings.as_mut().and_then(Iterator::next);
   }
 
   fn advance_committed(&mut self) {
@@ -68,6 +70,19 @@ where
     pendings: BTreeMapIter<'a, K, EntryValue<V>>,
     committed: Iter<'a, K, V>,
     marker: Option<Marker<'a, C>>,
+  ) -> Self {
+    Self::new_in(Some(pendings), committed, marker)
+  }
+
+  /// Creates an iterator over the committed entries only, the pending writes are not merged.
+  pub fn committed_only(committed: Iter<'a, K, V>, marker: Option<Marker<'a, C>>) -> Self {
+    Self::new_in(None, committed, marker)
+  }
+
+  fn new_in(
+    pendings: Option<BTreeMapIter<'a, K, EntryValue<V>>>,
+    committed: Iter<'a, K, V>,
+    marker: Option<Marker<'a, C>>,
   ) -> Self {
     let mut iterator = TransactionIter {
       pendings,
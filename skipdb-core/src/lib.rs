@@ -6,6 +6,7 @@ extern crate alloc;
 
 use core::{
   borrow::Borrow,
+  mem,
   ops::{Bound, RangeBounds},
   sync::atomic::{AtomicU64, Ordering},
 };
@@ -32,6 +33,9 @@ use rev_range::*;
 pub mod types;
 use types::*;
 
+pub mod versions;
+use versions::*;
+
 #[doc(hidden)]
 pub trait Database<K, V>: AsSkipCore<K, V> {}
 
@@ -65,6 +69,11 @@ impl<K, V> SkipCore<K, V> {
     }
   }
 
+  #[inline]
+  pub fn last_discard_version(&self) -> u64 {
+    self.last_discard_version.load(Ordering::Acquire)
+  }
+
   #[inline]
   #[doc(hidden)]
   #[allow(private_interfaces)]
@@ -140,9 +149,44 @@ where
     }
   }
 
+  pub fn versions<Q>(&self, key: &Q, from: u64, to: u64) -> Versions<'_, K, V>
+  where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+  {
+    Versions {
+      ent: self.map.get(key),
+      cursor: Bound::Included(from),
+      to,
+    }
+  }
+
+  pub fn iter_versions(&self, from: u64, to: u64) -> VersionIter<'_, K, V> {
+    VersionIter {
+      iter: self.map.iter(),
+      current: None,
+      from,
+      to,
+    }
+  }
+
   pub fn iter(&self, version: u64) -> Iter<'_, K, V> {
     let iter = self.map.iter();
-    Iter { iter, version }
+    Iter {
+      iter,
+      version,
+      from: 0,
+    }
+  }
+
+  /// Returns an iterator over the entries whose latest version at `to` is written in
+  /// `[from, to]`, i.e. the keys modified in the window and not removed at `to`.
+  pub fn iter_window(&self, from: u64, to: u64) -> Iter<'_, K, V> {
+    Iter {
+      iter: self.map.iter(),
+      version: to,
+      from,
+    }
   }
 
   pub fn iter_rev(&self, version: u64) -> RevIter<'_, K, V> {
@@ -165,6 +209,19 @@ where
     }
   }
 
+  pub fn count_range<Q, R>(&self, range: R, version: u64) -> u64
+  where
+    K: Borrow<Q>,
+    R: RangeBounds<Q>,
+    Q: Ord + ?Sized,
+  {
+    self.range(range, version).count() as u64
+  }
+
+  pub fn key_count(&self, version: u64) -> u64 {
+    self.iter(version).count() as u64
+  }
+
   pub fn range_rev<Q, R>(&self, range: R, version: u64) -> RevRange<'_, Q, R, K, V>
   where
     K: Borrow<Q>,
@@ -183,7 +240,9 @@ where
   K: Ord + Send + 'static,
   V: Send + 'static,
 {
-  pub fn compact(&self, new_discard_version: u64) {
+  pub fn compact(&self, new_discard_version: u64) -> CompactionStats {
+    let mut versions_removed = 0u64;
+    let mut keys_removed = 0u64;
     match self
       .last_discard_version
       .fetch_update(Ordering::SeqCst, Ordering::Acquire, |val| {
@@ -197,7 +256,7 @@ where
       // if we fail to insert the new discard version,
       // which means there is another thread that is compacting the database.
       // To avoid run multiple compacting at the same time, we just return.
-      Err(_) => return,
+      Err(_) => return CompactionStats::default(),
     }
 
     for ent in self.map.iter() {
@@ -222,7 +281,10 @@ where
             // try to lock the entry.
             if values.try_lock() {
               // we get the lock, then we can remove the whole key.
-              ent.remove();
+              if ent.remove() {
+                versions_removed += values.len() as u64;
+                keys_removed += 1;
+              }
 
               // unlock the entry.
               values.unlock();
@@ -234,7 +296,7 @@ where
           let mut prev = newest.prev();
           while let Some(ent) = prev {
             prev = ent.prev();
-            ent.remove();
+            versions_removed += ent.remove() as u64;
           }
           continue;
         }
@@ -253,7 +315,7 @@ where
         // remove all values that are smaller than the new discard version.
         while let Some(ent) = bound {
           bound = ent.prev();
-          ent.remove();
+          versions_removed += ent.remove() as u64;
         }
       } else {
         // we do not have any value in the entry, then we can try to remove the whole key.
@@ -261,12 +323,18 @@ where
         // try to lock the entry.
         if values.try_lock() {
           // we get the lock, then we can remove the whole key.
-          ent.remove();
+          keys_removed += ent.remove() as u64;
 
           // unlock the entry.
           values.unlock();
         }
       }
     }
+
+    CompactionStats {
+      versions_removed,
+      bytes_reclaimed: versions_removed * mem::size_of::<(u64, Option<V>)>() as u64
+        + keys_removed * mem::size_of::<(K, Values<V>)>() as u64,
+    }
   }
 }
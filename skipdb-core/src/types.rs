@@ -71,11 +71,21 @@ impl<V> core::ops::Deref for Values<V> {
   }
 }
 
+/// The statistics of a compaction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompactionStats {
+  /// The number of the versions removed.
+  pub versions_removed: u64,
+  /// The estimated bytes reclaimed, only the inline size of the removed versions is counted,
+  /// the heap memory owned by the keys and values is not.
+  pub bytes_reclaimed: u64,
+}
+
 /// A reference to an entry in the write transaction.
 pub struct Entry<'a, K, V> {
-  ent: MapEntry<'a, u64, Option<V>>,
-  key: &'a K,
-  version: u64,
+  pub(crate) ent: MapEntry<'a, u64, Option<V>>,
+  pub(crate) key: &'a K,
+  pub(crate) version: u64,
 }
 
 impl<'a, K, V> Clone for Entry<'a, K, V> {
@@ -110,7 +120,7 @@ impl<'a, K, V> Entry<'a, K, V> {
 }
 
 /// A reference to an entry in the write transaction.
-pub struct ValueRef<'a, K, V>(Either<&'a V, Entry<'a, K, V>>);
+pub struct ValueRef<'a, K, V>(pub(crate) Either<&'a V, Entry<'a, K, V>>);
 
 impl<'a, K, V: core::fmt::Debug> core::fmt::Debug for ValueRef<'a, K, V> {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
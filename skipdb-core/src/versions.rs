@@ -0,0 +1,127 @@
+use either::Either;
+
+use super::*;
+
+use crossbeam_skiplist::map::{Entry as MapEntry, Iter as MapIter};
+
+/// A reference to a committed version of a key, which is either a value or a remove marker.
+pub struct VersionRef<'a, K, V> {
+  pub(crate) ent: MapEntry<'a, K, Values<V>>,
+  pub(crate) version: u64,
+}
+
+impl<'a, K, V> Clone for VersionRef<'a, K, V> {
+  #[inline]
+  fn clone(&self) -> Self {
+    Self {
+      ent: self.ent.clone(),
+      version: self.version,
+    }
+  }
+}
+
+impl<'a, K, V> VersionRef<'a, K, V> {
+  /// Returns the key of the version.
+  #[inline]
+  pub fn key(&self) -> &K {
+    self.ent.key()
+  }
+
+  /// Returns the version.
+  #[inline]
+  pub const fn version(&self) -> u64 {
+    self.version
+  }
+
+  /// Returns the value of the version, `None` means the key was removed at this version.
+  #[inline]
+  pub fn value(&self) -> Option<ValueRef<'_, K, V>> {
+    let ent = self.ent.value().get(&self.version)?;
+    ent.value().as_ref()?;
+    Some(ValueRef(Either::Right(types::Entry {
+      ent,
+      key: self.ent.key(),
+      version: self.version,
+    })))
+  }
+
+  /// Returns `true` if the key was removed at this version.
+  #[inline]
+  pub fn is_removed(&self) -> bool {
+    self.value().is_none()
+  }
+}
+
+/// An iterator over all the committed versions of a key in a version range,
+/// in ascending order of version.
+pub struct Versions<'a, K, V> {
+  pub(crate) ent: Option<MapEntry<'a, K, Values<V>>>,
+  pub(crate) cursor: Bound<u64>,
+  pub(crate) to: u64,
+}
+
+impl<'a, K, V> Iterator for Versions<'a, K, V> {
+  type Item = VersionRef<'a, K, V>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let ent = self.ent.as_ref()?;
+    let version = ent
+      .value()
+      .lower_bound(self.cursor.as_ref())
+      .map(|v| *v.key())
+      .filter(|v| *v <= self.to);
+
+    match version {
+      Some(version) => {
+        self.cursor = Bound::Excluded(version);
+        Some(VersionRef {
+          ent: ent.clone(),
+          version,
+        })
+      }
+      None => {
+        self.ent = None;
+        None
+      }
+    }
+  }
+}
+
+/// An iterator over all the committed versions of all keys in a version range,
+/// in ascending order of key, and descending order of version for the same key.
+pub struct VersionIter<'a, K, V> {
+  pub(crate) iter: MapIter<'a, K, Values<V>>,
+  pub(crate) current: Option<(MapEntry<'a, K, Values<V>>, Bound<u64>)>,
+  pub(crate) from: u64,
+  pub(crate) to: u64,
+}
+
+impl<'a, K, V> Iterator for VersionIter<'a, K, V>
+where
+  K: Ord,
+{
+  type Item = VersionRef<'a, K, V>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      if let Some((ent, cursor)) = &mut self.current {
+        let version = ent
+          .value()
+          .upper_bound(cursor.as_ref())
+          .map(|v| *v.key())
+          .filter(|v| *v >= self.from);
+
+        if let Some(version) = version {
+          *cursor = Bound::Excluded(version);
+          return Some(VersionRef {
+            ent: ent.clone(),
+            version,
+          });
+        }
+      }
+
+      let ent = self.iter.next()?;
+      self.current = Some((ent, Bound::Included(self.to)));
+    }
+  }
+}
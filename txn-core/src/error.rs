@@ -29,6 +29,56 @@ pub enum TransactionError<C: Error, P: Error> {
   #[cfg_attr(feature = "std", error("transaction is too large"))]
   LargeTxn,
 
+  /// Returned if the requested version or version range is invalid.
+  #[cfg_attr(feature = "std", error("invalid version range"))]
+  InvalidVersion,
+
+  /// Returned if waiting for a version timed out.
+  #[cfg_attr(feature = "std", error("timed out waiting for the version"))]
+  Timeout,
+
+  /// Returned if the transaction lags too many versions behind the latest version.
+  #[cfg_attr(
+    feature = "std",
+    error("transaction is stale, {lag} versions behind the latest version")
+  )]
+  StaleTransaction {
+    /// The number of versions the transaction lags behind.
+    lag: u64,
+  },
+
+  /// Returned if the transaction writes more distinct keys than its conflict keys limit.
+  #[cfg_attr(feature = "std", error("transaction has too many conflict keys"))]
+  TooManyConflictKeys,
+
+  /// Returned if a write transaction is created after the transaction manager is closed.
+  #[cfg_attr(feature = "std", error("transaction manager is closed"))]
+  Closed,
+
+  /// Returned if an operation involves transactions of different transaction managers.
+  #[cfg_attr(
+    feature = "std",
+    error("illegal operation on transactions of different transaction managers")
+  )]
+  Illegal,
+
+  /// Returned if a key is written more than once by a transaction which requires unique keys.
+  #[cfg_attr(feature = "std", error("transaction writes a key more than once"))]
+  DuplicateKey,
+
+  /// Returned if a transaction conflicts after its retry budget is used up, holds the number
+  /// of the retried conflicts.
+  #[cfg_attr(
+    feature = "std",
+    error("transaction conflict, {0} retries are exhausted")
+  )]
+  RetriesExhausted(u32),
+
+  /// Returned if a spawned task or the wait for a version is canceled before it finishes,
+  /// e.g. when the async runtime is shutting down.
+  #[cfg_attr(feature = "std", error("transaction operation is canceled"))]
+  Canceled,
+
   /// Returned if the transaction manager error occurs.
   #[cfg_attr(feature = "std", error("transaction manager error: {0}"))]
   Pwm(P),
@@ -46,6 +96,24 @@ impl<C: Error, P: Error> core::fmt::Display for TransactionError<C, P> {
       Self::Conflict => write!(f, "transaction conflict, please retry"),
       Self::Discard => write!(f, "transaction has been discarded, please create a new one"),
       Self::LargeTxn => write!(f, "transaction is too large"),
+      Self::InvalidVersion => write!(f, "invalid version range"),
+      Self::Timeout => write!(f, "timed out waiting for the version"),
+      Self::StaleTransaction { lag } => write!(
+        f,
+        "transaction is stale, {} versions behind the latest version",
+        lag
+      ),
+      Self::TooManyConflictKeys => write!(f, "transaction has too many conflict keys"),
+      Self::Closed => write!(f, "transaction manager is closed"),
+      Self::Illegal => write!(
+        f,
+        "illegal operation on transactions of different transaction managers"
+      ),
+      Self::DuplicateKey => write!(f, "transaction writes a key more than once"),
+      Self::RetriesExhausted(retries) => {
+        write!(f, "transaction conflict, {} retries are exhausted", retries)
+      }
+      Self::Canceled => write!(f, "transaction operation is canceled"),
       Self::Pwm(e) => write!(f, "transaction manager error: {}", e),
       Self::Cm(e) => write!(f, "conflict manager error: {}", e),
     }
@@ -66,6 +134,54 @@ impl<C: Error, P: Error> TransactionError<C, P> {
   }
 }
 
+impl<C: Error, P: Error> From<P> for TransactionError<C, P> {
+  /// Wraps the pending manager error, so that `?` can be used on the results of
+  /// [`Pwm`](crate::sync::Pwm) methods directly.
+  #[inline]
+  fn from(err: P) -> Self {
+    Self::Pwm(err)
+  }
+}
+
+/// Error type for the oracle of the transaction manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum OracleError {
+  /// Returned if the timestamp would overflow `u64`.
+  #[cfg_attr(feature = "std", error("timestamp overflow"))]
+  Overflow,
+
+  /// Returned if the operation requires no read or write transaction to be active.
+  #[cfg_attr(feature = "std", error("there are active transactions"))]
+  ActiveTransactions,
+
+  /// Returned if the version is not greater than the current version.
+  #[cfg_attr(
+    feature = "std",
+    error("version is not greater than the current version")
+  )]
+  InvalidVersion,
+
+  /// Returned if the active transactions are not finished in time.
+  #[cfg_attr(
+    feature = "std",
+    error("timed out waiting for the active transactions to finish")
+  )]
+  Timeout,
+}
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for OracleError {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::Overflow => write!(f, "timestamp overflow"),
+      Self::ActiveTransactions => write!(f, "there are active transactions"),
+      Self::InvalidVersion => write!(f, "version is not greater than the current version"),
+      Self::Timeout => write!(f, "timed out waiting for the active transactions to finish"),
+    }
+  }
+}
+
 /// Error type for write transaction.
 pub enum WtmError<C: Error, P: Error, E: Error> {
   /// Returned if the transaction error occurs.
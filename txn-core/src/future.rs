@@ -291,6 +291,10 @@ pub trait AsyncPwm: Sized {
   /// The iterator type that consumes the pending writes.
   type IntoIter: Iterator<Item = (Self::Key, EntryValue<Self::Value>)>;
 
+  /// The extra bytes of each entry for its version and the index overhead, which are
+  /// added to [`estimate_size`](AsyncPwm::estimate_size) when checking the size of the transaction.
+  const VERSION_OVERHEAD: u64 = 16;
+
   /// Create a new pending manager with the given options.
   fn new(options: Self::Options) -> impl Future<Output = Result<Self, Self::Error>>;
 
@@ -356,8 +360,53 @@ pub trait AsyncPwm: Sized {
   /// Returns an iterator over the pending writes.
   fn iter(&self) -> impl Future<Output = Self::Iter<'_>>;
 
+  /// Returns an iterator over the pending writes in the descending order of the keys.
+  ///
+  /// The default implementation collects and sorts the pending writes, the implementations
+  /// ordered by the keys should override it to iterate in reverse directly.
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn iter_rev(
+    &self,
+  ) -> impl Future<Output = impl Iterator<Item = (&Self::Key, &EntryValue<Self::Value>)>>
+  where
+    Self::Key: Ord,
+  {
+    async move {
+      let mut pendings = alloc::vec::Vec::from_iter(self.iter().await);
+      pendings.sort_unstable_by(|a, b| b.0.cmp(a.0));
+      pendings.into_iter()
+    }
+  }
+
   /// Returns an iterator that consumes the pending writes.
   fn into_iter(self) -> impl Future<Output = Self::IntoIter>;
+
+  /// Consumes the pending writes and collects all the entries into a [`Vec`](alloc::vec::Vec).
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn drain_to_vec(
+    self,
+  ) -> impl Future<Output = alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)>> {
+    async move { self.into_iter().await.collect() }
+  }
+
+  /// Clones all the entries of the pending writes into a [`Vec`](alloc::vec::Vec).
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn to_vec(&self) -> impl Future<Output = alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)>>
+  where
+    Self::Key: Clone,
+    Self::Value: Clone,
+  {
+    async move {
+      self
+        .iter()
+        .await
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect()
+    }
+  }
 }
 
 /// An trait that can be used to get a range over the pending writes.
@@ -463,6 +512,18 @@ pub trait AsyncPwmComparable: AsyncPwm {
     Q: Ord + ?Sized;
 }
 
+/// An trait that can be used to fork the pending writes, e.g. for speculative execution.
+pub trait AsyncPwmSnapshot: AsyncPwm {
+  /// The immutable copy of the pending writes.
+  type Snapshot: IntoIterator<Item = (Self::Key, EntryValue<Self::Value>)>;
+
+  /// Returns an immutable copy of the pending writes.
+  fn snapshot(&self) -> impl Future<Output = Result<Self::Snapshot, Self::Error>>;
+
+  /// Creates a new pending writes manager from the snapshot.
+  fn from_snapshot(snapshot: Self::Snapshot) -> impl Future<Output = Result<Self, Self::Error>>;
+}
+
 impl<T> AsyncCm for T
 where
   T: Cm,
@@ -552,6 +613,8 @@ where
 
   type IntoIter = <T as Pwm>::IntoIter;
 
+  const VERSION_OVERHEAD: u64 = <T as Pwm>::VERSION_OVERHEAD;
+
   async fn new(options: Self::Options) -> Result<Self, Self::Error> {
     <T as Pwm>::new(options)
   }
@@ -618,9 +681,31 @@ where
     <T as Pwm>::iter(self)
   }
 
+  #[cfg(feature = "alloc")]
+  async fn iter_rev(&self) -> impl Iterator<Item = (&Self::Key, &EntryValue<Self::Value>)>
+  where
+    Self::Key: Ord,
+  {
+    <T as Pwm>::iter_rev(self)
+  }
+
   async fn into_iter(self) -> Self::IntoIter {
     <T as Pwm>::into_iter(self)
   }
+
+  #[cfg(feature = "alloc")]
+  async fn drain_to_vec(self) -> alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)> {
+    <T as Pwm>::drain_to_vec(self)
+  }
+
+  #[cfg(feature = "alloc")]
+  async fn to_vec(&self) -> alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)>
+  where
+    Self::Key: Clone,
+    Self::Value: Clone,
+  {
+    <T as Pwm>::to_vec(self)
+  }
 }
 
 impl<T> AsyncPwmRange for T
@@ -753,3 +838,18 @@ where
     <T as PwmEquivalent>::remove_entry_equivalent(self, key)
   }
 }
+
+impl<T> AsyncPwmSnapshot for T
+where
+  T: PwmSnapshot,
+{
+  type Snapshot = <T as PwmSnapshot>::Snapshot;
+
+  async fn snapshot(&self) -> Result<Self::Snapshot, Self::Error> {
+    <T as PwmSnapshot>::snapshot(self)
+  }
+
+  async fn from_snapshot(snapshot: Self::Snapshot) -> Result<Self, Self::Error> {
+    <T as PwmSnapshot>::from_snapshot(snapshot)
+  }
+}
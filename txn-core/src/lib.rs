@@ -24,6 +24,8 @@ pub type DefaultHasher = core::hash::BuildHasherDefault<ahash::AHasher>;
 pub mod types {
   use cheap_clone::CheapClone;
   use core::cmp::{self, Reverse};
+  #[cfg(feature = "ttl")]
+  use core::time::Duration;
 
   /// The reference of the [`Entry`].
   #[derive(Debug, PartialEq, Eq, Hash)]
@@ -163,6 +165,10 @@ pub mod types {
     pub version: u64,
     /// The data of the entry.
     pub data: EntryData<K, V>,
+    /// The time-to-live hint of the entry, the database is responsible for enforcing it.
+    #[cfg(feature = "ttl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ttl")))]
+    pub ttl: Option<Duration>,
   }
 
   impl<K: Ord, V: Eq> PartialOrd for Entry<K, V> {
@@ -192,6 +198,8 @@ pub mod types {
       Self {
         version: self.version,
         data: self.data.clone(),
+        #[cfg(feature = "ttl")]
+        ttl: self.ttl,
       }
     }
   }
@@ -205,11 +213,32 @@ pub mod types {
       Self {
         version: self.version,
         data: self.data.cheap_clone(),
+        #[cfg(feature = "ttl")]
+        ttl: self.ttl,
       }
     }
   }
 
   impl<K, V> Entry<K, V> {
+    /// Creates a new entry with the given version and data.
+    #[inline]
+    pub const fn new(version: u64, data: EntryData<K, V>) -> Self {
+      Self {
+        version,
+        data,
+        #[cfg(feature = "ttl")]
+        ttl: None,
+      }
+    }
+
+    /// Returns the time-to-live hint of the entry.
+    #[cfg(feature = "ttl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ttl")))]
+    #[inline]
+    pub const fn ttl(&self) -> Option<Duration> {
+      self.ttl
+    }
+
     /// Returns the data contained by the entry.
     #[inline]
     pub const fn data(&self) -> &EntryData<K, V> {
@@ -239,24 +268,44 @@ pub mod types {
 
     /// Split the entry into its key and [`EntryValue`].
     pub fn split(self) -> (K, EntryValue<V>) {
-      let Entry { data, version } = self;
+      let Entry {
+        data,
+        version,
+        #[cfg(feature = "ttl")]
+        ttl,
+      } = self;
 
       let (key, value) = match data {
         EntryData::Insert { key, value } => (key, Some(value)),
         EntryData::Remove(key) => (key, None),
       };
-      (key, EntryValue { value, version })
+      (
+        key,
+        EntryValue {
+          value,
+          version,
+          #[cfg(feature = "ttl")]
+          ttl,
+        },
+      )
     }
 
     /// Unsplit the key and [`EntryValue`] into an entry.
     pub fn unsplit(key: K, value: EntryValue<V>) -> Self {
-      let EntryValue { value, version } = value;
+      let EntryValue {
+        value,
+        version,
+        #[cfg(feature = "ttl")]
+        ttl,
+      } = value;
       Entry {
         data: match value {
           Some(value) => EntryData::Insert { key, value },
           None => EntryData::Remove(key),
         },
         version,
+        #[cfg(feature = "ttl")]
+        ttl,
       }
     }
   }
@@ -268,6 +317,10 @@ pub mod types {
     pub version: u64,
     /// The value of the entry.
     pub value: Option<V>,
+    /// The time-to-live hint of the entry.
+    #[cfg(feature = "ttl")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "ttl")))]
+    pub ttl: Option<Duration>,
   }
 
   impl<V> Clone for EntryValue<V>
@@ -278,6 +331,8 @@ pub mod types {
       Self {
         version: self.version,
         value: self.value.clone(),
+        #[cfg(feature = "ttl")]
+        ttl: self.ttl,
       }
     }
   }
@@ -290,6 +345,8 @@ pub mod types {
       Self {
         version: self.version,
         value: self.value.cheap_clone(),
+        #[cfg(feature = "ttl")]
+        ttl: self.ttl,
       }
     }
   }
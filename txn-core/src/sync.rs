@@ -254,6 +254,10 @@ pub trait Pwm: Sized {
   /// The options type used to create the pending manager.
   type Options;
 
+  /// The extra bytes of each entry for its version and the index overhead, which are
+  /// added to [`estimate_size`](Pwm::estimate_size) when checking the size of the transaction.
+  const VERSION_OVERHEAD: u64 = 16;
+
   /// Create a new pending manager with the given options.
   fn new(options: Self::Options) -> Result<Self, Self::Error>;
 
@@ -306,9 +310,42 @@ pub trait Pwm: Sized {
   /// Returns an iterator over the pending writes.
   fn iter(&self) -> Self::Iter<'_>;
 
+  /// Returns an iterator over the pending writes in the descending order of the keys.
+  ///
+  /// The default implementation collects and sorts the pending writes, the implementations
+  /// ordered by the keys should override it to iterate in reverse directly.
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn iter_rev(&self) -> impl Iterator<Item = (&Self::Key, &EntryValue<Self::Value>)>
+  where
+    Self::Key: Ord,
+  {
+    let mut pendings = alloc::vec::Vec::from_iter(self.iter());
+    pendings.sort_unstable_by(|a, b| b.0.cmp(a.0));
+    pendings.into_iter()
+  }
+
   /// Returns an iterator that consumes the pending writes.
   fn into_iter(self) -> Self::IntoIter;
 
+  /// Consumes the pending writes and collects all the entries into a [`Vec`](alloc::vec::Vec).
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn drain_to_vec(self) -> alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)> {
+    self.into_iter().collect()
+  }
+
+  /// Clones all the entries of the pending writes into a [`Vec`](alloc::vec::Vec).
+  #[cfg(feature = "alloc")]
+  #[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+  fn to_vec(&self) -> alloc::vec::Vec<(Self::Key, EntryValue<Self::Value>)>
+  where
+    Self::Key: Clone,
+    Self::Value: Clone,
+  {
+    self.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+  }
+
   /// Rollback the pending writes.
   fn rollback(&mut self) -> Result<(), Self::Error>;
 }
@@ -344,6 +381,18 @@ pub trait PwmEquivalentRange: PwmRange + PwmEquivalent {
     R: RangeBounds<T>;
 }
 
+/// An trait that can be used to fork the pending writes, e.g. for speculative execution.
+pub trait PwmSnapshot: Pwm {
+  /// The immutable copy of the pending writes.
+  type Snapshot: IntoIterator<Item = (Self::Key, EntryValue<Self::Value>)>;
+
+  /// Returns an immutable copy of the pending writes.
+  fn snapshot(&self) -> Result<Self::Snapshot, Self::Error>;
+
+  /// Creates a new pending writes manager from the snapshot.
+  fn from_snapshot(snapshot: Self::Snapshot) -> Result<Self, Self::Error>;
+}
+
 /// An optimized version of the [`Pwm`] trait that if your pending writes manager is depend on hash.
 pub trait PwmEquivalent: Pwm {
   /// Optimized version of [`Pwm::get`] that accepts borrowed keys.
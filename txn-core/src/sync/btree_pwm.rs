@@ -56,7 +56,11 @@ where
 
   #[inline]
   fn estimate_size(&self, _entry: &Entry<Self::Key, Self::Value>) -> u64 {
-    core::mem::size_of::<Self::Key>() as u64 + core::mem::size_of::<Self::Value>() as u64
+    let size =
+      core::mem::size_of::<Self::Key>() as u64 + core::mem::size_of::<Self::Value>() as u64;
+    #[cfg(feature = "ttl")]
+    let size = size + core::mem::size_of::<Option<core::time::Duration>>() as u64;
+    size
   }
 
   #[inline]
@@ -93,6 +97,11 @@ where
     BTreeMap::iter(self)
   }
 
+  #[inline]
+  fn iter_rev(&self) -> impl Iterator<Item = (&K, &EntryValue<V>)> {
+    BTreeMap::iter(self).rev()
+  }
+
   #[inline]
   fn into_iter(self) -> Self::IntoIter {
     core::iter::IntoIterator::into_iter(self)
@@ -105,6 +114,24 @@ where
   }
 }
 
+impl<K, V> PwmSnapshot for BTreeMap<K, EntryValue<V>>
+where
+  K: Ord + Clone,
+  V: Clone,
+{
+  type Snapshot = Self;
+
+  #[inline]
+  fn snapshot(&self) -> Result<Self::Snapshot, Self::Error> {
+    Ok(self.clone())
+  }
+
+  #[inline]
+  fn from_snapshot(snapshot: Self::Snapshot) -> Result<Self, Self::Error> {
+    Ok(snapshot)
+  }
+}
+
 impl<K, V> PwmRange for BTreeMap<K, EntryValue<V>>
 where
   K: Ord,
@@ -59,6 +59,77 @@ impl<K, S: Clone> Clone for HashCm<K, S> {
   }
 }
 
+impl<K, S> HashCm<K, S> {
+  /// Returns an iterator over the fingerprints of the keys written by the transaction,
+  /// which are tested against the reads of other transactions at commit time.
+  #[inline]
+  pub fn conflict_keys_iter(&self) -> impl Iterator<Item = u64> + '_ {
+    self.conflict_keys.iter().copied()
+  }
+
+  /// Returns an iterator over the fingerprints of the keys read by the transaction.
+  ///
+  /// Reads by iteration are not tied to a key, see [`HashCm::is_iterated`].
+  #[inline]
+  pub fn reads_iter(&self) -> impl Iterator<Item = u64> + '_ {
+    self.reads.iter().filter_map(|read| match read {
+      Read::Single(fp) => Some(*fp),
+      Read::All => None,
+    })
+  }
+
+  /// Returns the hasher used to fingerprint the keys.
+  #[inline]
+  pub fn hasher(&self) -> &S {
+    self.conflict_keys.hasher()
+  }
+
+  /// Marks a key as read by its pre-computed fingerprint.
+  ///
+  /// # Warning
+  /// The fingerprint must be the one [`hasher`](HashCm::hasher) computes for the key,
+  /// otherwise conflicts on the key will silently go undetected.
+  #[inline]
+  pub fn mark_read_fingerprint(&mut self, fp: u64) {
+    self.reads.push(Read::Single(fp));
+  }
+
+  /// Marks a key as a conflict key by its pre-computed fingerprint.
+  ///
+  /// # Warning
+  /// The fingerprint must be the one [`hasher`](HashCm::hasher) computes for the key,
+  /// otherwise conflicts on the key will silently go undetected.
+  #[inline]
+  pub fn mark_conflict_fingerprint(&mut self, fp: u64)
+  where
+    S: BuildHasher,
+  {
+    self.conflict_keys.insert(fp);
+  }
+
+  /// Forgets all but the last `n` reads tracked by the transaction, returns the number of
+  /// the forgotten reads. An iteration of the database counts as one read.
+  #[inline]
+  pub fn retain_last_reads(&mut self, n: usize) -> usize {
+    let evicted = self.reads.len().saturating_sub(n);
+    self.reads.drain(..evicted);
+    evicted
+  }
+
+  /// Clears the reads tracked by the transaction, the conflict keys are kept.
+  #[inline]
+  pub fn clear_reads(&mut self) {
+    self.reads.clear();
+  }
+
+  /// Returns `true` if the transaction has iterated the database, in which case it
+  /// conflicts with any other transaction that writes.
+  #[inline]
+  pub fn is_iterated(&self) -> bool {
+    self.reads.iter().any(|read| matches!(read, Read::All))
+  }
+}
+
 impl<K, S> Cm for HashCm<K, S>
 where
   S: BuildHasher,
@@ -184,4 +255,61 @@ mod test {
     cm2.mark_conflict_equivalent(&2);
     assert!(cm.has_conflict(&cm2));
   }
+
+  #[test]
+  fn test_hash_cm_iters() {
+    use crate::sync::CmIter;
+
+    let mut cm = HashCm::<u64>::new(HashCmOptions::new(
+      std::collections::hash_map::RandomState::new(),
+    ))
+    .unwrap();
+    cm.mark_read(&1);
+    cm.mark_conflict(&2);
+    cm.mark_conflict(&2);
+    assert_eq!(cm.reads_iter().count(), 1);
+    assert_eq!(cm.conflict_keys_iter().count(), 1);
+    assert!(!cm.is_iterated());
+
+    cm.mark_iter();
+    assert_eq!(cm.reads_iter().count(), 1);
+    assert!(cm.is_iterated());
+  }
+
+  #[test]
+  fn test_hash_cm_fingerprint() {
+    use core::hash::BuildHasher;
+
+    let mut cm = HashCm::<u64>::new(HashCmOptions::new(
+      std::collections::hash_map::RandomState::new(),
+    ))
+    .unwrap();
+    cm.mark_read(&1);
+    let mut cm2 = cm.clone();
+    cm2.mark_conflict_fingerprint(cm2.hasher().hash_one(1u64));
+    assert!(cm.has_conflict(&cm2));
+  }
+
+  #[test]
+  fn test_hash_cm_retain_last_reads() {
+    let mut cm = HashCm::<u64>::new(HashCmOptions::new(
+      std::collections::hash_map::RandomState::new(),
+    ))
+    .unwrap();
+    cm.mark_read(&1);
+    cm.mark_read(&2);
+    cm.mark_read(&3);
+    cm.mark_conflict(&4);
+    assert_eq!(cm.retain_last_reads(1), 2);
+    assert_eq!(cm.reads_iter().count(), 1);
+    assert_eq!(cm.conflict_keys_iter().count(), 1);
+    assert_eq!(cm.retain_last_reads(1), 0);
+
+    let mut cm2 = cm.clone();
+    cm2.rollback().unwrap();
+    cm2.mark_conflict(&1);
+    assert!(!cm.has_conflict(&cm2));
+    cm2.mark_conflict(&3);
+    assert!(cm.has_conflict(&cm2));
+  }
 }
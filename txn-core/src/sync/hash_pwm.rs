@@ -55,7 +55,11 @@ where
 
   #[inline]
   fn estimate_size(&self, _entry: &Entry<Self::Key, Self::Value>) -> u64 {
-    core::mem::size_of::<Self::Key>() as u64 + core::mem::size_of::<Self::Value>() as u64
+    let size =
+      core::mem::size_of::<Self::Key>() as u64 + core::mem::size_of::<Self::Value>() as u64;
+    #[cfg(feature = "ttl")]
+    let size = size + core::mem::size_of::<Option<core::time::Duration>>() as u64;
+    size
   }
 
   #[inline]
@@ -104,6 +108,25 @@ where
   }
 }
 
+impl<K, V, S> PwmSnapshot for IndexMap<K, EntryValue<V>, S>
+where
+  K: Eq + Hash + Clone,
+  V: Clone,
+  S: BuildHasher + Default + Clone,
+{
+  type Snapshot = Self;
+
+  #[inline]
+  fn snapshot(&self) -> Result<Self::Snapshot, Self::Error> {
+    Ok(self.clone())
+  }
+
+  #[inline]
+  fn from_snapshot(snapshot: Self::Snapshot) -> Result<Self, Self::Error> {
+    Ok(snapshot)
+  }
+}
+
 impl<K, V, S> PwmEquivalent for IndexMap<K, EntryValue<V>, S>
 where
   K: Eq + Hash,
@@ -26,6 +26,24 @@ pub use write::*;
 
 pub use txn_core::{sync::*, types::*};
 
+/// Options for the watermarks used by the transaction manager to track
+/// the pending reads and commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatermarkOptions {
+  /// The capacity of the mark channel of each watermark, beginning or finishing
+  /// a transaction blocks when the channel is full. Default is `100`.
+  pub channel_capacity: usize,
+}
+
+impl Default for WatermarkOptions {
+  #[inline]
+  fn default() -> Self {
+    Self {
+      channel_capacity: 100,
+    }
+  }
+}
+
 /// A multi-writer multi-reader MVCC, ACID, Serializable Snapshot Isolation transaction manager.
 pub struct Tm<K, V, C, P> {
   inner: Arc<Oracle<C>>,
@@ -73,13 +91,21 @@ impl<K, V, C, P> Tm<K, V, C, P> {
   /// and the current version (provided by the database).
   #[inline]
   pub fn new(name: &str, current_version: u64) -> Self {
+    Self::with_options(name, current_version, WatermarkOptions::default())
+  }
+
+  /// Create a new transaction manager with the given name, the current version
+  /// and the options of the watermarks.
+  #[inline]
+  pub fn with_options(name: &str, current_version: u64, opts: WatermarkOptions) -> Self {
     Self {
       inner: Arc::new({
         let next_ts = current_version;
-        let orc = Oracle::new(
+        let orc = Oracle::with_options(
           format!("{}.pending_reads", name).into(),
           format!("{}.txn_timestamps", name).into(),
           next_ts,
+          opts,
         );
         orc.read_mark.done(next_ts).unwrap();
         orc.txn_mark.done(next_ts).unwrap();
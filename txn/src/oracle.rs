@@ -7,6 +7,8 @@ use txn_core::sync::Cm;
 
 use wmark::{Closer, WaterMark};
 
+use super::WatermarkOptions;
+
 #[derive(Debug)]
 pub(super) struct OracleInner<C> {
   next_txn_ts: u64,
@@ -130,10 +132,11 @@ where
 
 impl<C> Oracle<C> {
   #[inline]
-  pub fn new(
+  pub fn with_options(
     read_mark_name: Cow<'static, str>,
     txn_mark_name: Cow<'static, str>,
     next_txn_ts: u64,
+    opts: WatermarkOptions,
   ) -> Self {
     let closer = Closer::new(2);
     let mut orc = Self {
@@ -143,8 +146,8 @@ impl<C> Oracle<C> {
         last_cleanup_ts: 0,
         committed_txns: TinyVec::new(),
       }),
-      read_mark: WaterMark::new(read_mark_name),
-      txn_mark: WaterMark::new(txn_mark_name),
+      read_mark: WaterMark::with_capacity(read_mark_name, opts.channel_capacity),
+      txn_mark: WaterMark::with_capacity(txn_mark_name, opts.channel_capacity),
       closer,
     };
 
@@ -117,10 +117,7 @@ where
   /// reads happening before this timestamp would be unaffected. Any reads after
   /// this commit would see the deletion.
   pub fn remove(&mut self, key: K) -> Result<(), TransactionError<C::Error, P::Error>> {
-    self.modify(Entry {
-      data: EntryData::Remove(key),
-      version: 0,
-    })
+    self.modify(Entry::new(self.read_ts, EntryData::Remove(key)))
   }
 
   /// Rolls back the transaction.
@@ -183,6 +180,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get<'a, 'b: 'a>(
     &'a mut self,
     key: &'b K,
@@ -198,11 +198,6 @@ where
       .get(key)
       .map_err(TransactionError::Pwm)?
     {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -239,10 +234,10 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. If callback is provided, Badger will return immediately after checking
-  /// for conflicts. Writes to the database will happen in the background.  If
-  /// there is a conflict, an error will be returned and the callback will not
-  /// run. If there are no conflicts, the callback will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    for conflicts. Writes to the database will happen in the background.  If
+  ///    there is a conflict, an error will be returned and the callback will not
+  ///    run. If there are no conflicts, the callback will be called in the
+  ///    background upon successful completion of writes or any error during write.
   pub fn commit<F, E>(&mut self, apply: F) -> Result<(), WtmError<C::Error, P::Error, E>>
   where
     F: FnOnce(OneOrMore<Entry<K, V>>) -> Result<(), E>,
@@ -359,6 +354,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_equivalent<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -378,11 +376,6 @@ where
       .get_entry_equivalent(key)
       .map_err(TransactionError::Pwm)?
     {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -455,6 +448,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_comparable_cm_equivalent_pm<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -474,11 +470,6 @@ where
       .get_entry_equivalent(key)
       .map_err(TransactionError::Pwm)?
     {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -579,6 +570,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_comparable<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -598,11 +592,6 @@ where
       .get_entry_comparable(key)
       .map_err(TransactionError::Pwm)?
     {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -675,6 +664,9 @@ where
 
   /// Looks for the key in the pending writes, if such key is not in the pending writes,
   /// the end user can read the key from the database.
+  ///
+  /// A key removed by the transaction is in the pending writes, so `Some` entry whose
+  /// value is `None` is returned for it rather than `None`.
   pub fn get_equivalent_cm_comparable_pm<'a, 'b: 'a, Q>(
     &'a mut self,
     key: &'b Q,
@@ -694,11 +686,6 @@ where
       .get_entry_comparable(key)
       .map_err(TransactionError::Pwm)?
     {
-      // If the value is None, it means that the key is removed.
-      if e.value.is_none() {
-        return Ok(None);
-      }
-
       // Fulfill from buffer.
       Ok(Some(EntryRef {
         data: match &e.value {
@@ -736,9 +723,9 @@ where
   /// 4. Batch up all writes, write them to database.
   ///
   /// 5. Return immediately after checking for conflicts.
-  /// If there is a conflict, an error will be returned immediately and the callback will not
-  /// run. If there are no conflicts, the callback will be called in the
-  /// background upon successful completion of writes or any error during write.
+  ///    If there is a conflict, an error will be returned immediately and the callback will not
+  ///    run. If there are no conflicts, the callback will be called in the
+  ///    background upon successful completion of writes or any error during write.
   ///
   /// If error does not occur, the transaction is successfully committed. In case of an error, the DB
   /// should not be updated (The implementors of [`Database`] must promise this), so there's no need for any rollback.
@@ -800,10 +787,7 @@ where
     key: K,
     value: V,
   ) -> Result<(), TransactionError<C::Error, P::Error>> {
-    let ent = Entry {
-      data: EntryData::Insert { key, value },
-      version: self.read_ts,
-    };
+    let ent = Entry::new(self.read_ts, EntryData::Insert { key, value });
 
     self.modify(ent)
   }
@@ -820,7 +804,7 @@ where
 
     let cnt = self.count + 1;
     // Extra bytes for the version in key.
-    let size = self.size + pending_writes.estimate_size(&ent);
+    let size = self.size + pending_writes.estimate_size(&ent) + P::VERSION_OVERHEAD;
     if cnt >= pending_writes.max_batch_entries() || size >= pending_writes.max_batch_size() {
       return Err(TransactionError::LargeTxn);
     }
@@ -984,6 +968,29 @@ mod tests {
     assert_eq!(wtm.get_equivalent("6").unwrap(), None);
   }
 
+  #[test]
+  fn with_options() {
+    let tm = Tm::<String, u64, HashCm<String>, IndexMapPwm<String, u64>>::with_options(
+      "test",
+      0,
+      WatermarkOptions {
+        channel_capacity: 1,
+      },
+    );
+
+    for i in 0..10 {
+      let mut wtm = tm.write(Default::default(), Default::default()).unwrap();
+      wtm.insert(i.to_string(), i).unwrap();
+      wtm
+        .commit(|ents| {
+          assert_eq!(ents.len(), 1);
+          Ok::<_, Infallible>(())
+        })
+        .unwrap();
+    }
+    assert_eq!(tm.version(), 10);
+  }
+
   struct TestCm<K> {
     conflict_keys: BTreeSet<usize>,
     reads: BTreeSet<usize>,
@@ -1053,6 +1060,19 @@ mod tests {
     }
   }
 
+  #[test]
+  fn get_pending_remove() {
+    let tm = Tm::<String, u64, HashCm<String>, IndexMapPwm<String, u64>>::new("test", 0);
+    let mut wtm = tm.write(Default::default(), Default::default()).unwrap();
+    wtm.insert("1".into(), 1).unwrap();
+    wtm.commit(|_| Ok::<_, Infallible>(())).unwrap();
+
+    let mut wtm = tm.write(Default::default(), Default::default()).unwrap();
+    wtm.remove("1".into()).unwrap();
+    assert!(wtm.get(&"1".to_owned()).unwrap().unwrap().value().is_none());
+    assert!(wtm.get_equivalent("1").unwrap().unwrap().value().is_none());
+  }
+
   #[test]
   fn wtm2() {
     let tm = Tm::<Arc<u64>, u64, TestCm<Arc<u64>>, IndexMapPwm<Arc<u64>, u64>>::new("test", 0);
@@ -346,7 +346,7 @@ impl<S: AsyncSpawner> AsyncWaterMark<S> {
   }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "tokio"))]
 mod tests {
   use super::*;
   use core::future::Future;
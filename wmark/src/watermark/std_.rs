@@ -168,7 +168,16 @@ impl WaterMark {
   /// **Note**: Before using the watermark, you must call `init` to start the background thread.
   #[inline]
   pub fn new(name: Cow<'static, str>) -> Self {
-    let (mark_tx, mark_rx) = bounded(100);
+    Self::with_capacity(name, 100)
+  }
+
+  /// Create a new WaterMark with the given name and the capacity of the mark channel,
+  /// `begin` and `done` block when the channel is full.
+  ///
+  /// **Note**: Before using the watermark, you must call `init` to start the background thread.
+  #[inline]
+  pub fn with_capacity(name: Cow<'static, str>, capacity: usize) -> Self {
+    let (mark_tx, mark_rx) = bounded(capacity);
     Self {
       inner: Arc::new(Inner {
         done_until: CachePadded::new(AtomicU64::new(0)),